@@ -0,0 +1,702 @@
+//! Finding mistakes in how scenes are connected without running the game.
+//! This reads the compiled bytecode rather than the sources, so that every function is known by
+//! its full item path, even nested ones and ones with the same name in different places, and
+//! only real calls are followed.
+//! Scenes are found from `state.add_scenes([("name", function), ..])`, and the scenes that each
+//! one can go to from string literals in `state.goto("name")` and `Button::new(text, "name")`
+//! however `Button` is named, including in closures and in functions that the scene calls. The
+//! default of `state.ask_choice_timed(buttons, seconds, "name", callback)` is followed as well, as
+//! it is usually a scene name like the buttons' values.
+//! Scene names that are computed, rather than written as a literal, can't be followed, so this
+//! only gives warnings rather than errors.
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use runestick::{DebugInfo, Hash, Inst, Unit};
+use serde::Serialize;
+
+use crate::diagnostics::{line_column, Diagnostic};
+
+/// Where something is in the sources.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub file: String,
+    /// Byte offsets in the source
+    pub start: usize,
+    pub end: usize,
+    /// Both starting at 1
+    pub line: usize,
+    pub column: usize,
+}
+impl Location {
+    fn diagnostic(&self, message: String) -> Diagnostic {
+        Diagnostic {
+            file: self.file.clone(),
+            start: self.start,
+            end: self.end,
+            line: self.line,
+            column: self.column,
+            message,
+        }
+    }
+}
+
+/// A scene that can be gone to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exit {
+    pub target: String,
+    /// The text of the button it is in, if it is in one and the text is a literal
+    pub label: Option<String>,
+    /// Where the scene name was written
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneNode {
+    pub name: String,
+    /// The full item path of the function that displays the scene, such as `forest::leave` for a
+    /// function nested in `forest`
+    pub function: String,
+    /// The file that the function is in
+    pub file: String,
+    /// Where the scene was registered
+    pub location: Location,
+    pub exits: Vec<Exit>,
+    /// Whether the scene can also leave in a way that can't be followed, such as a `goto` with a
+    /// computed name, or `back` and `load`.
+    pub has_computed_exit: bool,
+}
+impl SceneNode {
+    /// What the scene is keyed by in `to_dot` and `to_json`: the file and item path of its
+    /// function, like `game.rune:town`.
+    pub fn id(&self) -> String {
+        format!("{}:{}", self.file, self.function)
+    }
+}
+
+/// The scenes, and how they are connected.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SceneGraph {
+    /// The scenes that `entry` goes to
+    pub start: Vec<Exit>,
+    pub scenes: Vec<SceneNode>,
+    /// Every scene name that is gone to anywhere in the scripts, even in functions that are
+    /// never called.
+    pub gotos: Vec<Exit>,
+}
+impl SceneGraph {
+    /// Find the scenes in a unit compiled from the sources.
+    /// The unit needs its debug info, which rune includes by default, as that is what splits the
+    /// bytecode into functions and says where it came from. Without it the graph is empty.
+    pub fn from_unit(unit: &Unit, sources: &rune::Sources) -> Self {
+        let debug = match unit.debug_info() {
+            Some(debug) => debug,
+            None => return Self::default(),
+        };
+        let instructions: Vec<Inst> = unit.iter_instructions().collect();
+        let starts: Vec<(usize, Hash, String)> = (0..instructions.len())
+            .filter_map(|ip| {
+                debug
+                    .function_at(ip)
+                    .map(|(hash, signature)| (ip, hash, signature.path.to_string()))
+            })
+            .collect();
+
+        let mut order = Vec::new();
+        let mut functions = HashMap::new();
+        for (index, (start, hash, path)) in starts.iter().enumerate() {
+            let end = starts
+                .get(index + 1)
+                .map_or(instructions.len(), |(end, _, _)| *end);
+            let function = Function {
+                unit,
+                debug,
+                sources,
+                instructions: &instructions[*start..end],
+                offset: *start,
+            };
+            order.push(*hash);
+            functions.insert(*hash, function.scan(path.clone()));
+        }
+
+        // Each function's exits, along with those of any functions it calls. Exits reached
+        // through a button's closure are labeled with the button's text.
+        let exits_of = |hash: Hash| {
+            let mut exits = Vec::new();
+            let mut has_computed_exit = false;
+            let mut visited = HashSet::new();
+            let mut pending = vec![(hash, None)];
+            while let Some((hash, label)) = pending.pop() {
+                if !visited.insert(hash) {
+                    continue;
+                }
+                if let Some(info) = functions.get(&hash) {
+                    let info: &FunctionInfo = info;
+                    exits.extend(info.exits.iter().map(|exit| Exit {
+                        label: exit.label.clone().or_else(|| label.clone()),
+                        ..exit.clone()
+                    }));
+                    has_computed_exit |= info.has_computed_exit;
+                    pending.extend(info.calls.iter().map(|(hash, call_label)| {
+                        (*hash, call_label.clone().or_else(|| label.clone()))
+                    }));
+                }
+            }
+            (exits, has_computed_exit)
+        };
+
+        let (start, _) = exits_of(Hash::type_hash(&["entry"]));
+        let scenes = order
+            .iter()
+            .flat_map(|hash| functions[hash].registered.iter())
+            .map(|(name, hash, location)| {
+                let (exits, has_computed_exit) = exits_of(*hash);
+                let (function, file) = functions.get(hash).map_or_else(
+                    || (String::new(), String::new()),
+                    |info| (info.path.clone(), info.file.clone()),
+                );
+                SceneNode {
+                    name: name.clone(),
+                    function,
+                    file,
+                    location: location.clone(),
+                    exits,
+                    has_computed_exit,
+                }
+            })
+            .collect();
+        let gotos = order
+            .iter()
+            .flat_map(|hash| functions[hash].exits.iter().cloned())
+            .collect();
+        Self {
+            start,
+            scenes,
+            gotos,
+        }
+    }
+
+    pub fn scene(&self, name: &str) -> Option<&SceneNode> {
+        self.scenes.iter().find(|scene| scene.name == name)
+    }
+
+    /// The names of every scene that can be reached from `entry` by following the exits.
+    pub fn reachable(&self) -> BTreeSet<String> {
+        let mut reachable = BTreeSet::new();
+        let mut pending: Vec<&str> = self.start.iter().map(|exit| exit.target.as_str()).collect();
+        while let Some(name) = pending.pop() {
+            if !reachable.insert(name.to_owned()) {
+                continue;
+            }
+            if let Some(scene) = self.scene(name) {
+                pending.extend(scene.exits.iter().map(|exit| exit.target.as_str()));
+            }
+        }
+        reachable
+    }
+
+    /// Warnings for gotos to scenes that were never registered, registered scenes that nothing
+    /// goes to, and scenes that have no way out.
+    pub fn check(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for exit in self.gotos.iter() {
+            if self.scene(&exit.target).is_none() {
+                diagnostics.push(exit.location.diagnostic(format!(
+                    "Goes to scene '{}', which is never registered with `add_scenes`",
+                    exit.target
+                )));
+            }
+        }
+
+        let reachable = self.reachable();
+        for scene in self.scenes.iter() {
+            if !reachable.contains(&scene.name) {
+                diagnostics.push(scene.location.diagnostic(format!(
+                    "Scene '{}' is registered, but nothing goes to it",
+                    scene.name
+                )));
+            }
+            if scene.exits.is_empty() && !scene.has_computed_exit {
+                diagnostics.push(scene.location.diagnostic(format!(
+                    "Scene '{}' has no exits, so the game ends there",
+                    scene.name
+                )));
+            }
+        }
+        diagnostics
+    }
+
+    /// The graph in Graphviz's DOT format, such as for `dot -Tsvg`.
+    /// Scenes are boxes keyed by their `id`, with a double border if they have no exits. Edges
+    /// are labeled with the text of the button they are in. Scenes that are gone to but never
+    /// registered are dashed and red.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph scenes {\n    node [shape=box];\n");
+        dot.push_str("    \"<entry>\" [shape=point];\n");
+        for exit in unique_edges(&self.start) {
+            for target in self.node_ids(&exit.target) {
+                dot.push_str(&format!(
+                    "    \"<entry>\" -> {};\n",
+                    dot_edge_end(&target, exit)
+                ));
+            }
+        }
+        for scene in self.scenes.iter() {
+            let id = dot_string(&scene.id());
+            let has_exit = !scene.exits.is_empty() || scene.has_computed_exit;
+            dot.push_str(&format!(
+                "    {} [label={}{}];\n",
+                id,
+                dot_string(&scene.name),
+                if has_exit { "" } else { ", peripheries=2" }
+            ));
+            for exit in unique_edges(&scene.exits) {
+                for target in self.node_ids(&exit.target) {
+                    dot.push_str(&format!("    {} -> {};\n", id, dot_edge_end(&target, exit)));
+                }
+            }
+        }
+        let unregistered: BTreeSet<&str> = self
+            .gotos
+            .iter()
+            .map(|exit| exit.target.as_str())
+            .filter(|target| self.scene(target).is_none())
+            .collect();
+        for target in unregistered {
+            dot.push_str(&format!(
+                "    {} [style=dashed, color=red];\n",
+                dot_string(target)
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The graph as a json adjacency list, with scenes keyed by their `id`:
+    /// `{"start": [..], "scenes": {"game.rune:town": {"name": "town", "exits": [{"target":
+    /// "forest", "label": ..}], ..}}}`. Exits name the scene they go to.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        let json = JsonGraph {
+            start: unique_edges(&self.start).map(JsonExit::new).collect(),
+            scenes: self
+                .scenes
+                .iter()
+                .map(|scene| {
+                    let exits = unique_edges(&scene.exits).map(JsonExit::new).collect();
+                    (
+                        scene.id(),
+                        JsonScene {
+                            name: &scene.name,
+                            function: &scene.function,
+                            file: &scene.file,
+                            exits,
+                            has_computed_exit: scene.has_computed_exit,
+                        },
+                    )
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&json)
+    }
+
+    /// The ids of the scenes with the name, or the name itself if it was never registered.
+    fn node_ids(&self, name: &str) -> Vec<String> {
+        let ids: Vec<_> = self
+            .scenes
+            .iter()
+            .filter(|scene| scene.name == name)
+            .map(SceneNode::id)
+            .collect();
+        if ids.is_empty() {
+            vec![name.to_owned()]
+        } else {
+            ids
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonGraph<'a> {
+    start: Vec<JsonExit<'a>>,
+    scenes: BTreeMap<String, JsonScene<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonScene<'a> {
+    name: &'a str,
+    function: &'a str,
+    file: &'a str,
+    exits: Vec<JsonExit<'a>>,
+    has_computed_exit: bool,
+}
+
+#[derive(Serialize)]
+struct JsonExit<'a> {
+    target: &'a str,
+    label: Option<&'a str>,
+}
+impl<'a> JsonExit<'a> {
+    fn new(exit: &'a Exit) -> Self {
+        Self {
+            target: &exit.target,
+            label: exit.label.as_deref(),
+        }
+    }
+}
+
+/// The exits, without any that go to the same scene with the same label as an earlier one.
+fn unique_edges(exits: &[Exit]) -> impl Iterator<Item = &Exit> {
+    let mut seen = BTreeSet::new();
+    exits
+        .iter()
+        .filter(move |exit| seen.insert((&exit.target, &exit.label)))
+}
+
+/// The target of an edge, along with its label.
+fn dot_edge_end(target: &str, exit: &Exit) -> String {
+    match &exit.label {
+        Some(label) => format!("{} [label={}]", dot_string(target), dot_string(label)),
+        None => dot_string(target),
+    }
+}
+
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[derive(Debug, Default)]
+struct FunctionInfo {
+    /// The full item path
+    path: String,
+    file: String,
+    exits: Vec<Exit>,
+    has_computed_exit: bool,
+    /// The functions and closures that it calls or refers to, which may not be in the scripts,
+    /// along with the text of the button that a closure is the value of
+    calls: Vec<(Hash, Option<String>)>,
+    /// The scenes it registers with `add_scenes`, with the function that displays each
+    registered: Vec<(String, Hash, Location)>,
+}
+
+/// The bytecode of one function. Arguments are pushed in order right before the call, so a
+/// literal argument is found by looking back from the call.
+struct Function<'a> {
+    unit: &'a Unit,
+    debug: &'a DebugInfo,
+    sources: &'a rune::Sources,
+    instructions: &'a [Inst],
+    /// Where the function starts in the unit
+    offset: usize,
+}
+impl Function<'_> {
+    fn scan(&self, path: String) -> FunctionInfo {
+        let button_new = Hash::type_hash(&[crate::engine::MODULE_NAME, "Button", "new"]);
+        let computed = [
+            Hash::of("back"),
+            Hash::of("load"),
+            Hash::of("load_from"),
+            Hash::of("quickload"),
+        ];
+        let mut info = FunctionInfo {
+            path,
+            file: self.location(0).file,
+            ..FunctionInfo::default()
+        };
+        // Entries that look like `("name", function)`, until the call they are given to
+        let mut entries = Vec::new();
+        for (index, inst) in self.instructions.iter().enumerate() {
+            match *inst {
+                Inst::Tuple { count: 2 } => {
+                    if let Some((name, hash)) = self.entry(index) {
+                        entries.push((name, hash, self.location(index - 2)));
+                    }
+                }
+                Inst::Fn { hash } | Inst::Closure { hash, .. } => {
+                    if self.entry(index + 1).is_none() {
+                        // Closures are usually called later, such as by a choice, so what they
+                        // do counts toward the function that makes them
+                        info.calls.push((hash, self.button_text(index)));
+                    }
+                }
+                Inst::Call { hash, .. } if hash == button_new => {
+                    // `Button::new(text, "scene")`, where the text may be any expression
+                    if let Some(target) = self.literal(index.wrapping_sub(1)) {
+                        let label = self.button_text(index - 1);
+                        info.exits.push(self.exit(index - 1, target, label));
+                    }
+                }
+                Inst::Call { hash, .. } => {
+                    info.calls.push((hash, None));
+                    entries.clear();
+                }
+                Inst::CallInstance { hash, .. } if hash == Hash::of("goto") => {
+                    match self.literal(index.wrapping_sub(1)) {
+                        Some(target) => info.exits.push(self.exit(index - 1, target, None)),
+                        None => info.has_computed_exit = true,
+                    }
+                }
+                Inst::CallInstance { hash, .. } if computed.contains(&hash) => {
+                    info.has_computed_exit = true
+                }
+                Inst::CallInstance { hash, .. } if hash == Hash::of("add_scenes") => {
+                    info.registered.append(&mut entries)
+                }
+                Inst::CallInstance { hash, .. } if hash == Hash::of("ask_choice_timed") => {
+                    // The default value is the argument before the callback
+                    let default = self.value_start(index.wrapping_sub(1)).wrapping_sub(1);
+                    if let Some(target) = self.literal(default) {
+                        info.exits.push(self.exit(default, target, None));
+                    }
+                }
+                Inst::CallInstance { .. } => entries.clear(),
+                _ => {}
+            }
+        }
+        info
+    }
+
+    /// The string that the instruction pushes, if it pushes a literal.
+    fn literal(&self, index: usize) -> Option<String> {
+        match self.instructions.get(index)? {
+            Inst::String { slot } => self
+                .unit
+                .lookup_string(*slot)
+                .ok()
+                .map(|string| string.as_str().to_owned()),
+            _ => None,
+        }
+    }
+
+    /// The scene name and function of the `("name", function)` tuple made by the instruction.
+    fn entry(&self, index: usize) -> Option<(String, Hash)> {
+        if !matches!(self.instructions.get(index)?, Inst::Tuple { count: 2 }) {
+            return None;
+        }
+        let hash = match self.instructions.get(index.checked_sub(1)?)? {
+            Inst::Fn { hash } | Inst::Closure { hash, count: 0 } => *hash,
+            _ => return None,
+        };
+        Some((self.literal(index.checked_sub(2)?)?, hash))
+    }
+
+    /// The first instruction of the value that ends at the instruction, which is simple enough
+    /// to tell for literals, variables and closures, the values that matter here.
+    fn value_start(&self, end: usize) -> usize {
+        match self.instructions.get(end) {
+            // Its captured variables are pushed first
+            Some(Inst::Closure { count, .. }) => end.wrapping_sub(*count),
+            _ => end,
+        }
+    }
+
+    /// The text of the button that the value ending at the instruction is the value of, if the
+    /// text is a literal.
+    fn button_text(&self, end: usize) -> Option<String> {
+        let button_new = Hash::type_hash(&[crate::engine::MODULE_NAME, "Button", "new"]);
+        match self.instructions.get(end + 1)? {
+            Inst::Call { hash, .. } if *hash == button_new => {
+                self.literal(self.value_start(end).checked_sub(1)?)
+            }
+            _ => None,
+        }
+    }
+
+    fn exit(&self, index: usize, target: String, label: Option<String>) -> Exit {
+        Exit {
+            target,
+            label,
+            location: self.location(index),
+        }
+    }
+
+    /// Where the code that compiled to the instruction is.
+    fn location(&self, index: usize) -> Location {
+        let debug = self.debug.instruction_at(self.offset + index);
+        let source = debug.and_then(|debug| self.sources.get(debug.source_id));
+        match (debug, source) {
+            (Some(debug), Some(source)) => {
+                let (line, column) = line_column(source.as_str(), debug.span.start);
+                Location {
+                    file: source.name().to_owned(),
+                    start: debug.span.start,
+                    end: debug.span.end,
+                    line,
+                    column,
+                }
+            }
+            _ => Location {
+                file: String::from("<unknown>"),
+                start: 0,
+                end: 0,
+                line: 0,
+                column: 0,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME: &str = r#"use Engine::Button;
+pub fn entry(state) {
+    state.add_scenes([("town", town), ("forest", forest), ("cave", cave), ("lost", lost)]);
+    state.goto("town");
+}
+fn town(state) {
+    // state.goto("ghost");
+    state.set_text("state.goto(\"ghost\")");
+    state.ask_choice([
+        Button::new("Leave Town", "forest"),
+        Button::new("Stay", || stay(state)),
+    ], |next| state.goto(next));
+}
+fn stay(state) {
+    state.goto("town");
+}
+fn forest(state) {
+    state.ask_choice_timed([Button::new("Run", "town")], 5, "cave", |next| state.goto(next));
+}
+fn cave(state) {
+    state.set_text("The end");
+}
+fn lost(state) {
+    state.goto("nowhere");
+}
+"#;
+
+    fn graph(files: &[(&str, &str)]) -> SceneGraph {
+        let mut sources = crate::load_inline(files);
+        let (graph, _) = crate::scene_graph(&mut sources, &crate::RunOptions::default()).unwrap();
+        graph
+    }
+
+    /// The targets and labels of the exits, sorted as the order they are found in doesn't matter.
+    fn targets(exits: &[Exit]) -> Vec<(&str, Option<&str>)> {
+        let mut targets: Vec<_> = exits
+            .iter()
+            .map(|exit| (exit.target.as_str(), exit.label.as_deref()))
+            .collect();
+        targets.sort();
+        targets
+    }
+
+    #[test]
+    fn scene_graph() {
+        let graph = graph(&[("game.rune", GAME)]);
+        assert_eq!(targets(&graph.start), vec![("town", None)]);
+        let names: Vec<_> = graph.scenes.iter().map(|scene| &scene.name).collect();
+        assert_eq!(names, vec!["town", "forest", "cave", "lost"]);
+
+        let town = graph.scene("town").unwrap();
+        assert_eq!(
+            (town.function.as_str(), town.file.as_str()),
+            ("town", "game.rune")
+        );
+        assert_eq!(town.location.line, 3);
+        // Including the exit of `stay`, which the "Stay" button calls
+        assert_eq!(
+            targets(&town.exits),
+            vec![("forest", Some("Leave Town")), ("town", Some("Stay"))]
+        );
+        assert!(town.has_computed_exit);
+
+        let forest = graph.scene("forest").unwrap();
+        assert_eq!(
+            targets(&forest.exits),
+            vec![("cave", None), ("town", Some("Run"))]
+        );
+        let cave = graph.scene("cave").unwrap();
+        assert!(cave.exits.is_empty() && !cave.has_computed_exit);
+
+        let reachable: Vec<_> = graph.reachable().into_iter().collect();
+        assert_eq!(reachable, vec!["cave", "forest", "town"]);
+    }
+
+    const TOWN: &str = r#"use Engine::Button;
+pub fn entry(state) {
+    state.add_scenes([("town", town), ("forest", forest), ("cave", cave)]);
+    state.goto("town");
+}
+fn town(state) {
+    state.ask_choice([Button::new("Leave", || leave(state))], |next| state.goto(next));
+}
+fn leave(state) {
+    state.goto("forest");
+}
+fn cave(state) {
+    state.set_text("The end");
+}
+"#;
+
+    const FOREST: &str = r#"fn forest(state) {
+    fn leave(state) {
+        state.goto("cave");
+    }
+    state.ask_choice([
+        Engine::Button::new("Back", "town"),
+        Engine::Button::new("Leave", || leave(state)),
+    ], |next| state.goto(next));
+}
+"#;
+
+    #[test]
+    fn same_names_in_different_files() {
+        let graph = graph(&[("town.rune", TOWN), ("forest.rune", FOREST)]);
+        // Each `leave` only counts for the scene that calls it
+        let town = graph.scene("town").unwrap();
+        assert_eq!(targets(&town.exits), vec![("forest", Some("Leave"))]);
+        let forest = graph.scene("forest").unwrap();
+        assert_eq!(
+            (forest.function.as_str(), forest.file.as_str()),
+            ("forest", "forest.rune")
+        );
+        assert_eq!(
+            targets(&forest.exits),
+            vec![("cave", Some("Leave")), ("town", Some("Back"))]
+        );
+        assert!(graph.check().is_empty());
+
+        let dot = graph.to_dot();
+        assert!(
+            dot.contains("    \"town.rune:town\" -> \"forest.rune:forest\" [label=\"Leave\"];\n")
+        );
+        assert!(
+            dot.contains("    \"forest.rune:forest\" -> \"town.rune:cave\" [label=\"Leave\"];\n")
+        );
+        let json: serde_json::Value = serde_json::from_str(&graph.to_json().unwrap()).unwrap();
+        assert_eq!(json["scenes"]["forest.rune:forest"]["name"], "forest");
+    }
+
+    #[test]
+    fn check_graph() {
+        let graph = graph(&[("game.rune", GAME)]);
+        let diagnostics = graph.check();
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Goes to scene 'nowhere', which is never registered with `add_scenes`",
+                "Scene 'cave' has no exits, so the game ends there",
+                "Scene 'lost' is registered, but nothing goes to it",
+            ]
+        );
+        let nowhere = &diagnostics[0];
+        assert_eq!((nowhere.line, nowhere.column), (24, 16));
+        assert_eq!(&GAME[nowhere.start..nowhere.end], "\"nowhere\"");
+    }
+
+    #[test]
+    fn dot() {
+        let dot = graph(&[("game.rune", GAME)]).to_dot();
+        assert!(dot.contains("    \"<entry>\" -> \"game.rune:town\";\n"));
+        assert!(dot.contains("    \"game.rune:town\" [label=\"town\"];\n"));
+        assert!(dot
+            .contains("    \"game.rune:town\" -> \"game.rune:forest\" [label=\"Leave Town\"];\n"));
+        assert!(dot.contains("    \"game.rune:cave\" [label=\"cave\", peripheries=2];\n"));
+        assert!(dot.contains("    \"nowhere\" [style=dashed, color=red];\n"));
+    }
+}
@@ -0,0 +1,453 @@
+use std::{io, rc::Rc};
+
+use aleph_naught::{
+    engine::{locale::StringTable, replay::Replay, storage::FileStorage},
+    frontend::{headless::Headless, terminal::Terminal, tui::Tui, watch::Watch},
+    playthrough::Playthrough,
+    RunOptions,
+};
+
+const USAGE: &str = "Usage: aleph [options] [--] <file.rune>...
+       aleph test <playthrough>...
+       aleph check [options] [--] <file.rune>...
+       aleph graph [--json] [options] [--] <file.rune>...
+
+Options:
+    --tui                   Play full-screen, rather than line-by-line
+    --saves <dir>           Directory to keep saves in. Defaults to `saves`
+    --warnings-as-errors    Refuse to start if the scripts have any warnings
+    --record <file>         Write everything the player did to the file when the game ends
+    --replay <file>         Play back a recording before handing the game over to the player
+    --watch                 Reload the scripts whenever they change, keeping the scene and info
+    --language <lang>       Language to show the game in, from the `.lang` string tables next
+                            to the scripts. Defaults to that of `LANG`, if there is a table
+
+`aleph test` runs scripted playthroughs of games, see `src/playthrough.rs` for the format.
+`aleph check` compiles the scripts without running them, and warns about gotos to scenes that
+aren't registered, scenes that nothing goes to, and scenes with no way out.
+`aleph graph` prints how the scenes are connected, as Graphviz DOT or as json with `--json`.
+`check` and `graph` only take `--warnings-as-errors` and `--language`.
+Everything after `--` is a file, so a game in a file named like a command is run with
+`aleph -- test`.";
+
+/// What the binary was asked to do.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Run,
+    Test,
+    Check,
+    Graph,
+}
+impl Command {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "test" => Some(Self::Test),
+            "check" => Some(Self::Check),
+            "graph" => Some(Self::Graph),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Run => "run",
+            Self::Test => "test",
+            Self::Check => "check",
+            Self::Graph => "graph",
+        }
+    }
+}
+
+/// The parsed command line.
+#[derive(Debug)]
+struct Args {
+    command: Command,
+    options: RunOptions,
+    use_tui: bool,
+    watch: bool,
+    save_directory: String,
+    record_path: Option<String>,
+    replay_path: Option<String>,
+    json: bool,
+    files: Vec<String>,
+}
+impl Args {
+    /// Parse the arguments, without the binary's name. The command is the first argument that
+    /// isn't an option, unless it comes after `--`. `Ok(None)` if the usage was asked for.
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let mut parsed = Self {
+            command: Command::Run,
+            options: RunOptions::default(),
+            use_tui: false,
+            watch: false,
+            save_directory: "saves".to_owned(),
+            record_path: None,
+            replay_path: None,
+            json: false,
+            files: Vec::new(),
+        };
+        // The options that were given, to check that they apply to the command
+        let mut given = Vec::new();
+        let mut only_files = false;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if only_files {
+                parsed.files.push(arg);
+                continue;
+            }
+            if arg.starts_with("--") && arg != "--" {
+                given.push(arg.clone());
+            }
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("Expected a {} after '{}'", name, arg))
+            };
+            match arg.as_str() {
+                "--" => only_files = true,
+                "--tui" => parsed.use_tui = true,
+                "--watch" => parsed.watch = true,
+                "--json" => parsed.json = true,
+                "--warnings-as-errors" => parsed.options.warnings_as_errors = true,
+                "--saves" => parsed.save_directory = value("directory")?,
+                "--language" => parsed.options.language = Some(value("language")?),
+                "--record" => parsed.record_path = Some(value("file")?),
+                "--replay" => parsed.replay_path = Some(value("file")?),
+                "-h" | "--help" => return Ok(None),
+                _ if arg.starts_with("--") => return Err(format!("Unknown option: '{}'", arg)),
+                _ => match Command::from_name(&arg) {
+                    Some(command) if parsed.command == Command::Run && parsed.files.is_empty() => {
+                        parsed.command = command
+                    }
+                    _ => parsed.files.push(arg),
+                },
+            }
+        }
+
+        let allowed: &[&str] = match parsed.command {
+            Command::Run => &[
+                "--tui",
+                "--watch",
+                "--warnings-as-errors",
+                "--saves",
+                "--language",
+                "--record",
+                "--replay",
+            ],
+            Command::Test => &[],
+            Command::Check => &["--warnings-as-errors", "--language"],
+            Command::Graph => &["--json", "--warnings-as-errors", "--language"],
+        };
+        if let Some(option) = given
+            .iter()
+            .find(|option| !allowed.contains(&option.as_str()))
+        {
+            return Err(format!(
+                "'{}' can't be used with 'aleph {}'",
+                option,
+                parsed.command.name()
+            ));
+        }
+        if parsed.files.is_empty() {
+            return Err("Expected at least one file".to_owned());
+        }
+        Ok(Some(parsed))
+    }
+}
+
+fn main() {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            std::process::exit(1);
+        }
+    };
+    let passed = match args.command {
+        Command::Run => {
+            run_game(args);
+            return;
+        }
+        Command::Test => run_playthroughs(&args.files),
+        Command::Check => run_check(&args.files, &args.options),
+        Command::Graph => run_graph(&args.files, args.json, &args.options),
+    };
+    std::process::exit(if passed { 0 } else { 1 });
+}
+
+/// Play the game in the terminal, exiting the process if it couldn't be played.
+fn run_game(args: Args) {
+    let Args {
+        mut options,
+        use_tui,
+        watch,
+        save_directory,
+        record_path,
+        replay_path,
+        files,
+        ..
+    } = args;
+
+    let sources = match aleph_naught::load_files(&files) {
+        Ok(sources) => sources,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    options.locales = match aleph_naught::load_locales(&aleph_naught::find_locales(&files)) {
+        Ok(locales) => locales,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    if let Some(language) = &options.language {
+        if !options
+            .locales
+            .iter()
+            .any(|table| &table.language == language)
+        {
+            eprintln!("There is no string table for language '{}'", language);
+            std::process::exit(1);
+        }
+    } else {
+        options.language = system_language(&options.locales);
+    }
+    let storage = Rc::new(FileStorage::new(save_directory));
+    let game = match Headless::with_options(sources, storage, &options) {
+        Ok(game) => game,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    for diagnostic in game.diagnostics() {
+        eprintln!("{}", diagnostic);
+    }
+
+    if let Some(path) = replay_path {
+        let replay = std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| Replay::from_json(&text).map_err(|err| err.to_string()));
+        let replay = match replay {
+            Ok(replay) => replay,
+            Err(err) => {
+                eprintln!("Failed to read replay '{}': {}", path, err);
+                std::process::exit(1);
+            }
+        };
+        // The player carries on from wherever it stopped, so that they can see where it diverged
+        if let Err(err) = game.play_replay(&replay) {
+            eprintln!("{}", err);
+        }
+    }
+
+    let (result, replay) = if use_tui {
+        let mut tui = Tui::new(game, io::stdout());
+        if watch {
+            tui = tui.with_watch(Watch::new(&files, options.clone()));
+        }
+        let result = tui.run().map_err(|err| err.to_string());
+        (result, tui.game().replay())
+    } else {
+        let stdin = io::stdin();
+        let stdout = io::stdout();
+        let mut terminal = Terminal::new(game, stdin.lock(), stdout.lock());
+        if watch {
+            terminal = terminal.with_watch(Watch::new(&files, options.clone()));
+        }
+        let result = terminal.run().map_err(|err| err.to_string());
+        (result, terminal.game().replay())
+    };
+
+    // Write the recording even if the terminal failed, as that may be what is being reported
+    if let Some(path) = record_path {
+        let written = replay
+            .map_err(|err| err.to_string())
+            .and_then(|replay| replay.to_json().map_err(|err| err.to_string()))
+            .and_then(|json| std::fs::write(&path, json).map_err(|err| err.to_string()));
+        if let Err(err) = written {
+            eprintln!("Failed to write recording '{}': {}", path, err);
+        }
+    }
+
+    if let Err(err) = result {
+        eprintln!("Terminal error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Run each playthrough, printing how every step went. Returns whether they all passed.
+fn run_playthroughs(paths: &[String]) -> bool {
+    let mut failed = Vec::new();
+    for path in paths {
+        println!("{}", path);
+        let passed = match Playthrough::load(path) {
+            Ok(playthrough) => {
+                let report = playthrough.run();
+                print!("{}", report);
+                report.passed()
+            }
+            Err(err) => {
+                println!("  {}", err);
+                false
+            }
+        };
+        if !passed {
+            failed.push(path);
+        }
+    }
+
+    println!();
+    if failed.is_empty() {
+        println!("All {} playthroughs passed", paths.len());
+    } else {
+        println!("{} of {} playthroughs failed:", failed.len(), paths.len());
+        for path in failed {
+            println!("    {}", path);
+        }
+    }
+    failed.is_empty()
+}
+
+/// Print the warnings about the scripts. Returns whether there weren't any.
+fn run_check(files: &[String], options: &RunOptions) -> bool {
+    let result = aleph_naught::load_files(files)
+        .and_then(|mut sources| aleph_naught::check(&mut sources, options));
+    match result {
+        Ok(diagnostics) => {
+            for diagnostic in diagnostics.iter() {
+                println!("{}", diagnostic);
+            }
+            if diagnostics.is_empty() {
+                println!("No problems found");
+            }
+            diagnostics.is_empty()
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            false
+        }
+    }
+}
+
+/// Print the scene graph to stdout, so that it can be piped into `dot`.
+/// Returns whether the scripts compiled.
+fn run_graph(files: &[String], json: bool, options: &RunOptions) -> bool {
+    let result = aleph_naught::load_files(files)
+        .and_then(|mut sources| aleph_naught::scene_graph(&mut sources, options));
+    let graph = match result {
+        Ok((graph, _)) => graph,
+        Err(err) => {
+            eprintln!("{}", err);
+            return false;
+        }
+    };
+    if json {
+        match graph.to_json() {
+            Ok(json) => println!("{}", json),
+            Err(err) => {
+                eprintln!("Failed to write json: {}", err);
+                return false;
+            }
+        }
+    } else {
+        print!("{}", graph.to_dot());
+    }
+    true
+}
+
+/// The language of the system from `LANG`, such as `fr` for `fr_FR.UTF-8`, if there is a string
+/// table for it. Either the whole language with its region, or just the language, is looked for.
+fn system_language(locales: &[StringTable]) -> Option<String> {
+    let lang = std::env::var("LANG").ok()?;
+    let lang = lang.split('.').next().unwrap_or_default();
+    let primary = lang
+        .split(|c| c == '_' || c == '-')
+        .next()
+        .unwrap_or_default();
+    [lang.to_owned(), lang.replace('_', "-"), primary.to_owned()]
+        .iter()
+        .find(|language| locales.iter().any(|table| &&table.language == language))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        Args::parse(args.iter().map(|arg| (*arg).to_owned()))
+    }
+
+    #[test]
+    fn commands() {
+        let args = parse(&["--tui", "--saves", "dir", "game.rune"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(args.command, Command::Run);
+        assert!(args.use_tui);
+        assert_eq!(args.save_directory, "dir");
+        assert_eq!(args.files, vec!["game.rune".to_owned()]);
+
+        // Options can go before or after the command
+        let args = parse(&[
+            "--warnings-as-errors",
+            "check",
+            "--language",
+            "fr",
+            "a.rune",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(args.command, Command::Check);
+        assert!(args.options.warnings_as_errors);
+        assert_eq!(args.options.language.as_deref(), Some("fr"));
+        assert_eq!(args.files, vec!["a.rune".to_owned()]);
+
+        // Only the first argument that isn't an option is a command
+        let args = parse(&["--tui", "game.rune", "graph"]).unwrap().unwrap();
+        assert_eq!(args.command, Command::Run);
+        assert_eq!(args.files, vec!["game.rune".to_owned(), "graph".to_owned()]);
+        let args = parse(&["graph", "test"]).unwrap().unwrap();
+        assert_eq!(args.command, Command::Graph);
+        assert_eq!(args.files, vec!["test".to_owned()]);
+
+        // Anything after `--` is a file
+        let args = parse(&["--", "test", "--tui"]).unwrap().unwrap();
+        assert_eq!(args.command, Command::Run);
+        assert!(!args.use_tui);
+        assert_eq!(args.files, vec!["test".to_owned(), "--tui".to_owned()]);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse(&["--help", "a.rune"]).unwrap().is_none());
+        assert_eq!(
+            parse(&["check", "--tui", "a.rune"]).unwrap_err(),
+            "'--tui' can't be used with 'aleph check'"
+        );
+        assert_eq!(
+            parse(&["--json", "a.rune"]).unwrap_err(),
+            "'--json' can't be used with 'aleph run'"
+        );
+        // Options before the command still belong to it
+        assert_eq!(
+            parse(&["--record", "out.json", "test", "a.test"]).unwrap_err(),
+            "'--record' can't be used with 'aleph test'"
+        );
+        assert_eq!(
+            parse(&["a.rune", "--saves"]).unwrap_err(),
+            "Expected a directory after '--saves'"
+        );
+        assert_eq!(
+            parse(&["--bad", "a.rune"]).unwrap_err(),
+            "Unknown option: '--bad'"
+        );
+        assert_eq!(parse(&["test"]).unwrap_err(), "Expected at least one file");
+    }
+}
@@ -0,0 +1,55 @@
+use runestick::Spanned;
+
+/// A problem found in the scripts which doesn't stop the game from running, such as an unused
+/// variable. These are handed back to the host so that it can log or display them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// The name of the source it is in, which is the path or url for files
+    pub file: String,
+    /// Byte offsets of the start and end of the offending code in the source
+    pub start: usize,
+    pub end: usize,
+    /// Position of `start`, both starting at 1
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "warning: {}:{}:{}: {}",
+            self.file, self.line, self.column, self.message
+        )
+    }
+}
+
+/// Turn the warnings from compiling into diagnostics.
+pub fn from_warnings(warnings: &rune::Warnings, sources: &rune::Sources) -> Vec<Diagnostic> {
+    warnings
+        .iter()
+        .map(|warning| {
+            let span = warning.kind.span();
+            let source = sources.get(warning.source_id);
+            let file = source.map_or_else(|| "<unknown>".to_owned(), |s| s.name().to_owned());
+            let (line, column) = source.map_or((0, 0), |s| line_column(s.as_str(), span.start));
+            Diagnostic {
+                file,
+                start: span.start,
+                end: span.end,
+                line,
+                column,
+                message: warning.kind.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// The line and column, in chars, of the byte offset.
+pub(crate) fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = text.get(..offset).unwrap_or(text);
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let line = before.matches('\n').count() + 1;
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
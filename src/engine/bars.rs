@@ -0,0 +1,122 @@
+use runestick::{Any, Shared, Value};
+
+use super::error::EngineError;
+
+/// What a bar displays.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BarContent {
+    /// Markup, like the normal text
+    Text(String),
+    /// Rows of a label and a value, like `("Health", "10/20")`.
+    /// Rows which were given as a single string have an empty value.
+    Rows(Vec<(String, String)>),
+}
+impl BarContent {
+    /// Convert what a bar's render function returned.
+    /// This can be a string, or a vec whose items are strings or `(label, value)` tuples.
+    fn from_value(value: Value) -> Result<Self, String> {
+        match value {
+            Value::Vec(rows) => {
+                let rows = rows.borrow_ref().map_err(|err| err.to_string())?;
+                rows.iter()
+                    .map(row_from_value)
+                    .collect::<Result<_, _>>()
+                    .map(Self::Rows)
+            }
+            value => display_value(&value).map(Self::Text),
+        }
+    }
+}
+
+fn row_from_value(value: &Value) -> Result<(String, String), String> {
+    match value {
+        Value::Tuple(tuple) => {
+            let tuple = tuple.borrow_ref().map_err(|err| err.to_string())?;
+            match &tuple[..] {
+                [label, value] => Ok((display_value(label)?, display_value(value)?)),
+                _ => Err(format!(
+                    "Expected a row to be a (label, value) tuple, but it had {} items",
+                    tuple.len()
+                )),
+            }
+        }
+        value => Ok((display_value(value)?, String::new())),
+    }
+}
+
+/// Turn a simple value into text to display.
+pub(crate) fn display_value(value: &Value) -> Result<String, String> {
+    Ok(match value {
+        Value::String(text) => text.borrow_ref().map_err(|err| err.to_string())?.clone(),
+        Value::StaticString(text) => text.as_str().to_owned(),
+        Value::Integer(value) => value.to_string(),
+        Value::Float(value) => value.to_string(),
+        Value::Bool(value) => value.to_string(),
+        Value::Char(value) => value.to_string(),
+        value => {
+            let type_name = value
+                .type_info()
+                .map(|type_info| type_info.to_string())
+                .unwrap_or_else(|_| "unknown".to_owned());
+            return Err(format!("Can't display value of type '{}'", type_name));
+        }
+    })
+}
+
+/// A bar, as it should currently be displayed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedBar {
+    pub name: String,
+    pub content: BarContent,
+}
+
+#[derive(Debug)]
+struct Bar {
+    name: String,
+    /// Called with the info to get what the bar should display
+    render: runestick::Function,
+}
+
+/// Information that is always on display, like a health bar or inventory, outside of the scenes.
+/// They are kept in the order that they were added.
+#[derive(Debug, Default, Any)]
+pub struct Bars {
+    bars: Vec<Bar>,
+}
+impl Bars {
+    /// Add the bar, replacing any bar that already has the name.
+    pub fn add(&mut self, name: String, render: runestick::Function) {
+        match self.bars.iter_mut().find(|bar| bar.name == name) {
+            Some(bar) => bar.render = render,
+            None => self.bars.push(Bar { name, render }),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.bars.retain(|bar| bar.name != name);
+    }
+
+    /// Call every render function with the info.
+    pub fn render(
+        &self,
+        info: &Shared<runestick::Object>,
+    ) -> Result<Vec<RenderedBar>, EngineError> {
+        self.bars
+            .iter()
+            .map(|bar| {
+                let bar_error = |error: String| EngineError::Bar {
+                    name: bar.name.clone(),
+                    error,
+                };
+                let value: Value = bar
+                    .render
+                    .call((info.clone(),))
+                    .map_err(|err| bar_error(err.to_string()))?;
+                Ok(RenderedBar {
+                    name: bar.name.clone(),
+                    content: BarContent::from_value(value).map_err(bar_error)?,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,95 @@
+use super::save::SaveError;
+
+/// Something that went wrong while running the game, which stops it from continuing.
+/// These are reported to the player through `Frontend::show_error`, and returned to whatever is
+/// hosting the game, rather than panicking.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineError {
+    /// `State::goto` was given a scene which was never added with `add_scenes`.
+    UnknownScene(String),
+    /// A script failed while it was running.
+    Script {
+        /// The scene that the game was in, if it had entered one yet.
+        scene: Option<String>,
+        error: String,
+    },
+    /// The scripts failed to compile.
+    Compile(String),
+    /// The host gave bad or missing startup information.
+    Config(String),
+    /// The code of a source could not be fetched or read.
+    SourceFetch {
+        /// The url or path of the source
+        source: String,
+        /// The HTTP status, if the source was fetched and the server responded
+        status: Option<u16>,
+        error: String,
+    },
+    /// More than one source could not be fetched or read. Each of these is a `SourceFetch`.
+    SourceFetches(Vec<EngineError>),
+    /// A bar's render function failed, or returned something that can't be displayed.
+    Bar { name: String, error: String },
+    /// The frontend failed to display something, such as the elements for a choice.
+    Frontend(String),
+    /// Some part of the state was already in use, such as the scenes being borrowed.
+    Access(String),
+    /// The info could not be saved or restored, such as when going back to a previous scene.
+    Save(SaveError),
+}
+impl EngineError {
+    pub fn script(scene: Option<String>, error: runestick::VmError) -> Self {
+        Self::Script {
+            scene,
+            error: error.to_string(),
+        }
+    }
+}
+impl std::fmt::Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownScene(scene) => write!(f, "Failed to find scene: '{}'", scene),
+            Self::Script {
+                scene: Some(scene),
+                error,
+            } => write!(f, "[Error in Scene: '{}']: {}", scene, error),
+            Self::Script { scene: None, error } => write!(f, "[Error in Script]: {}", error),
+            Self::Compile(err) => write!(f, "Failed to compile scripts:\n{}", err),
+            Self::Config(err) => write!(f, "Bad startup information: {}", err),
+            Self::SourceFetch {
+                source,
+                status: Some(status),
+                error,
+            } => write!(
+                f,
+                "Failed to fetch source '{}' (status {}): {}",
+                source, status, error
+            ),
+            Self::SourceFetch {
+                source,
+                status: None,
+                error,
+            } => write!(f, "Failed to fetch source '{}': {}", source, error),
+            Self::SourceFetches(errors) => {
+                write!(f, "Failed to fetch {} sources:", errors.len())?;
+                for error in errors {
+                    write!(f, "\n{}", error)?;
+                }
+                Ok(())
+            }
+            Self::Bar { name, error } => write!(f, "[Error in Bar: '{}']: {}", name, error),
+            Self::Frontend(err) => write!(f, "Failed to display: {}", err),
+            Self::Access(err) => write!(f, "Failed to access state: {}", err),
+            Self::Save(err) => write!(f, "[Save Error]: {}", err),
+        }
+    }
+}
+impl From<runestick::AccessError> for EngineError {
+    fn from(error: runestick::AccessError) -> Self {
+        Self::Access(error.to_string())
+    }
+}
+impl From<SaveError> for EngineError {
+    fn from(error: SaveError) -> Self {
+        Self::Save(error)
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+
+use runestick::Any;
+
+use super::save::SaveData;
+
+/// How many choices back the history goes, unless changed with `State::set_history_limit`.
+pub const DEFAULT_HISTORY_LIMIT: usize = 32;
+
+/// The scene and a copy of the info from before each choice the player made, so that the game
+/// can go back to them. The most recent entry is from before the last choice.
+/// The info is kept in the same form as saves, so it is a copy that later changes don't affect.
+#[derive(Debug, Any)]
+pub struct History {
+    entries: VecDeque<SaveData>,
+    limit: usize,
+}
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            limit: DEFAULT_HISTORY_LIMIT,
+        }
+    }
+}
+impl History {
+    /// Record the game as it was before a choice, forgetting the oldest entry if it is over the
+    /// limit.
+    pub fn push(&mut self, entry: SaveData) {
+        self.entries.push_back(entry);
+        self.truncate();
+    }
+
+    /// Forget every entry, such as when the current info couldn't be recorded, as going back
+    /// would otherwise skip over the choice.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Remove the entry from before the last choice, returning it so that it can be restored.
+    pub fn pop(&mut self) -> Option<SaveData> {
+        self.entries.pop_back()
+    }
+
+    /// Remove the entries from the choices made in the scene, and the one from the choice that
+    /// led to it, returning that one so that the scene before can be restored. `None`, without
+    /// removing anything, if there is no scene before this one.
+    pub fn pop_previous(&mut self, scene: Option<&str>) -> Option<SaveData> {
+        let index = self.previous(scene)?;
+        self.entries.truncate(index + 1);
+        self.entries.pop_back()
+    }
+
+    /// Whether there is a scene before this one to go back to.
+    pub fn can_go_back(&self, scene: Option<&str>) -> bool {
+        self.previous(scene).is_some()
+    }
+
+    /// The index of the last entry from another scene than this one.
+    fn previous(&self, scene: Option<&str>) -> Option<usize> {
+        self.entries
+            .iter()
+            .rposition(|entry| entry.scene.as_deref() != scene)
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        while self.entries.len() > self.limit {
+            self.entries.pop_front();
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! Translations of the text in a game, kept in a string table for each language.
+//! A string table is a text file named after its language, like `en.lang`, with a `key = text`
+//! on each line. Lines starting with `#` are comments, and `\n` in the text is a line break.
+//! ```text
+//! # en.lang
+//! town.title = Town of Plenty
+//! greeting = Hello, {name}!
+//! apples.one = You have an apple.
+//! apples.other = You have {count} apples.
+//! ```
+//! Scripts get text with `state.tr("greeting", #{name: "Bob"})`, which replaces each `{name}`
+//! with the argument of that name (`{{` and `}}` are literal braces). If there is a `count`
+//! argument, then the variant of the key for its plural category in the language is used, such as
+//! `apples.one`, falling back to `apples.other` and then `apples`.
+//! Keys that are missing in the current language are taken from the first table, and keys missing
+//! from that are shown as the key itself, so that they can be noticed.
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use runestick::{Any, Value};
+
+use super::bars::display_value;
+
+/// The translations for one language.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringTable {
+    pub language: String,
+    strings: HashMap<String, String>,
+}
+impl StringTable {
+    pub fn parse(language: &str, text: &str) -> Result<Self, String> {
+        let mut strings = HashMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let separator = line
+                .find('=')
+                .ok_or_else(|| format!("line {}: Expected 'key = text'", index + 1))?;
+            let key = line[..separator].trim();
+            if key.is_empty() {
+                return Err(format!("line {}: Missing the key before '='", index + 1));
+            }
+            let text = line[separator + 1..]
+                .trim()
+                .replace("\\\\", "\u{0}")
+                .replace("\\n", "\n")
+                .replace('\u{0}', "\\");
+            strings.insert(key.to_owned(), text);
+        }
+        Ok(Self {
+            language: language.to_owned(),
+            strings,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(String::as_str)
+    }
+
+    /// The text for the key, picking the plural variant for `count` if there is one.
+    fn get_counted(&self, key: &str, count: Option<i64>) -> Option<&str> {
+        if let Some(count) = count {
+            let category = plural_category(&self.language, count);
+            let variant = self
+                .get(&format!("{}.{}", key, category))
+                .or_else(|| self.get(&format!("{}.other", key)));
+            if variant.is_some() {
+                return variant;
+            }
+        }
+        self.get(key)
+    }
+}
+
+/// The CLDR plural category of the number in the language, for the languages where it isn't
+/// just `one` for 1 and `other` for everything else.
+pub fn plural_category(language: &str, count: i64) -> &'static str {
+    let primary = language
+        .split(|c| c == '-' || c == '_')
+        .next()
+        .unwrap_or(language)
+        .to_ascii_lowercase();
+    let n = count.abs();
+    let (ones, tens) = (n % 10, n % 100);
+    match primary.as_str() {
+        "ja" | "zh" | "ko" | "th" | "vi" | "id" | "ms" => "other",
+        "fr" | "pt" if n <= 1 => "one",
+        "fr" | "pt" => "other",
+        "ru" | "uk" | "be" | "sr" | "hr" | "bs" => {
+            if ones == 1 && tens != 11 {
+                "one"
+            } else if (2..=4).contains(&ones) && !(12..=14).contains(&tens) {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        "pl" => {
+            if n == 1 {
+                "one"
+            } else if (2..=4).contains(&ones) && !(12..=14).contains(&tens) {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        "cs" | "sk" => match n {
+            1 => "one",
+            2..=4 => "few",
+            _ => "other",
+        },
+        _ if n == 1 => "one",
+        _ => "other",
+    }
+}
+
+/// The string tables, and which language is being shown. This is shared by every state.
+#[derive(Debug, Clone, Default, Any)]
+pub struct Locale {
+    /// The first table is the one that missing keys are taken from
+    tables: Rc<RefCell<Vec<StringTable>>>,
+    language: Rc<RefCell<Option<String>>>,
+}
+impl Locale {
+    /// `language` defaults to that of the first table.
+    pub fn new(tables: Vec<StringTable>, language: Option<String>) -> Self {
+        let language = language.or_else(|| tables.first().map(|table| table.language.clone()));
+        Self {
+            tables: Rc::new(RefCell::new(tables)),
+            language: Rc::new(RefCell::new(language)),
+        }
+    }
+
+    /// The language being shown, if there are any string tables.
+    pub fn language(&self) -> Option<String> {
+        self.language.borrow().clone()
+    }
+
+    /// Every language that there is a string table for.
+    pub fn languages(&self) -> Vec<String> {
+        self.tables
+            .borrow()
+            .iter()
+            .map(|table| table.language.clone())
+            .collect()
+    }
+
+    /// Check that there is a string table for the language, without switching to it.
+    pub fn check_language(&self, language: &str) -> Result<(), String> {
+        if self
+            .tables
+            .borrow()
+            .iter()
+            .any(|table| table.language == language)
+        {
+            Ok(())
+        } else {
+            Err(format!(
+                "There is no string table for language '{}'",
+                language
+            ))
+        }
+    }
+
+    /// Swap in new string tables, such as when they are reloaded. The language is kept if there
+    /// is still a table for it, otherwise it goes back to that of the first table.
+    pub fn set_tables(&self, tables: Vec<StringTable>) {
+        let mut language = self.language.borrow_mut();
+        if !tables
+            .iter()
+            .any(|table| Some(&table.language) == language.as_ref())
+        {
+            *language = tables.first().map(|table| table.language.clone());
+        }
+        *self.tables.borrow_mut() = tables;
+    }
+
+    pub fn set_language(&self, language: &str) -> Result<(), String> {
+        self.check_language(language)?;
+        *self.language.borrow_mut() = Some(language.to_owned());
+        Ok(())
+    }
+
+    /// The text for the key in the current language, with the arguments filled in.
+    /// `args` is an object of arguments, or `()` if there are none.
+    pub fn tr(&self, key: &str, args: Value) -> Result<String, runestick::Panic> {
+        let args = match args {
+            Value::Unit => runestick::Object::new(),
+            Value::Object(args) => args.borrow_ref().map_err(runestick::Panic::custom)?.clone(),
+            _ => {
+                return Err(runestick::Panic::custom(
+                    "Expected the arguments to be an object, like #{count: 2}",
+                ))
+            }
+        };
+        let count = match args.get("count") {
+            Some(Value::Integer(count)) => Some(*count),
+            _ => None,
+        };
+
+        let language = self.language.borrow();
+        let tables = self.tables.borrow();
+        let current = tables
+            .iter()
+            .find(|table| Some(&table.language) == language.as_ref());
+        let text = current
+            .and_then(|table| table.get_counted(key, count))
+            .or_else(|| {
+                tables
+                    .first()
+                    .and_then(|table| table.get_counted(key, count))
+            })
+            .unwrap_or(key);
+        interpolate(text, &args).map_err(runestick::Panic::custom)
+    }
+
+    pub fn register(module: &mut runestick::Module) -> Result<(), runestick::ContextError> {
+        module.ty::<Self>()?;
+        module.inst_fn("tr", Self::tr)?;
+        module.inst_fn("language", Self::language)?;
+        module.inst_fn("languages", Self::languages)?;
+        Ok(())
+    }
+}
+
+/// Replace each `{name}` in the text with the argument, leaving unknown names as they are.
+fn interpolate(text: &str, args: &runestick::Object) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find(|c| c == '{' || c == '}') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            result.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let name = if rest.starts_with('{') {
+            rest.find('}').map(|end| &rest[1..end])
+        } else {
+            None
+        };
+        match name.and_then(|name| args.get(name).map(|value| (name, value))) {
+            Some((name, value)) => {
+                result.push_str(&display_value(value)?);
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                result.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use runestick::Shared;
+
+    use super::*;
+
+    fn args(pairs: &[(&str, Value)]) -> runestick::Object {
+        let mut args = runestick::Object::new();
+        for (name, value) in pairs {
+            args.insert((*name).to_owned(), value.clone());
+        }
+        args
+    }
+
+    fn text(text: &str) -> Value {
+        Value::String(Shared::new(text.to_owned()))
+    }
+
+    #[test]
+    fn plural_categories() {
+        let cases: &[(&str, i64, &str)] = &[
+            ("en", 1, "one"),
+            ("en", 0, "other"),
+            ("en", -1, "one"),
+            ("en-US", 2, "other"),
+            ("fr", 0, "one"),
+            ("fr", 1, "one"),
+            ("fr", 2, "other"),
+            ("pt_BR", 1, "one"),
+            ("ja", 1, "other"),
+            ("ru", 1, "one"),
+            ("ru", 21, "one"),
+            ("ru", 11, "many"),
+            ("ru", 3, "few"),
+            ("ru", 13, "many"),
+            ("ru", 5, "many"),
+            ("pl", 1, "one"),
+            ("pl", 21, "many"),
+            ("pl", 22, "few"),
+            ("cs", 3, "few"),
+            ("cs", 5, "other"),
+        ];
+        for (language, count, category) in cases {
+            assert_eq!(
+                plural_category(language, *count),
+                *category,
+                "{} in {}",
+                count,
+                language
+            );
+        }
+    }
+
+    #[test]
+    fn parse_table() {
+        let table = StringTable::parse(
+            "en",
+            "# A comment\n\ngreeting = Hello, {name}!\nlines = one\\ntwo \\\\n\n",
+        )
+        .unwrap();
+        assert_eq!(table.get("greeting"), Some("Hello, {name}!"));
+        assert_eq!(table.get("lines"), Some("one\ntwo \\n"));
+        assert_eq!(table.get("missing"), None);
+        assert_eq!(
+            StringTable::parse("en", "ok = fine\nnot a string"),
+            Err("line 2: Expected 'key = text'".to_owned())
+        );
+        assert_eq!(
+            StringTable::parse("en", " = text"),
+            Err("line 1: Missing the key before '='".to_owned())
+        );
+    }
+
+    #[test]
+    fn interpolation() {
+        let values = args(&[("name", text("Bob")), ("count", Value::Integer(3))]);
+        assert_eq!(
+            interpolate("Hello, {name}! {count} {{x}} {missing} {name", &values).unwrap(),
+            "Hello, Bob! 3 {x} {missing} {name"
+        );
+        assert_eq!(interpolate("", &values).unwrap(), "");
+        let unit = args(&[("nothing", Value::Unit)]);
+        assert!(interpolate("{nothing}", &unit).is_err());
+    }
+
+    #[test]
+    fn translate() {
+        let en = StringTable::parse(
+            "en",
+            "title = Market\napples.one = An apple\napples.other = {count} apples",
+        )
+        .unwrap();
+        let fr =
+            StringTable::parse("fr", "apples.one = {count} pomme\napples = Des pommes").unwrap();
+        let locale = Locale::new(vec![en, fr], None);
+        assert_eq!(locale.language().as_deref(), Some("en"));
+        let count = |count| Value::Object(Shared::new(args(&[("count", Value::Integer(count))])));
+        assert_eq!(locale.tr("apples", count(1)).unwrap(), "An apple");
+        assert_eq!(locale.tr("apples", count(2)).unwrap(), "2 apples");
+
+        locale.set_language("fr").unwrap();
+        assert_eq!(locale.tr("apples", count(0)).unwrap(), "0 pomme");
+        // Without a variant for the category, nor an `other`, the plain key is used
+        assert_eq!(locale.tr("apples", count(2)).unwrap(), "Des pommes");
+        // Missing keys come from the first table, and then are shown as the key
+        assert_eq!(locale.tr("title", Value::Unit).unwrap(), "Market");
+        assert_eq!(locale.tr("nowhere", Value::Unit).unwrap(), "nowhere");
+
+        assert!(locale.set_language("de").is_err());
+        assert_eq!(locale.language().as_deref(), Some("fr"));
+        assert!(locale.tr("title", Value::Integer(1)).is_err());
+
+        // Reloaded tables keep the language while it still has one
+        let fr = StringTable::parse("fr", "title = Marché").unwrap();
+        let en = StringTable::parse("en", "title = Market").unwrap();
+        locale.set_tables(vec![en.clone(), fr]);
+        assert_eq!(locale.tr("title", Value::Unit).unwrap(), "Marché");
+        locale.set_tables(vec![en]);
+        assert_eq!(locale.language().as_deref(), Some("en"));
+        assert_eq!(locale.tr("title", Value::Unit).unwrap(), "Market");
+    }
+}
@@ -0,0 +1,1024 @@
+pub mod bars;
+pub mod error;
+pub mod history;
+pub mod locale;
+pub mod replay;
+pub mod save;
+pub mod storage;
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+    sync::Arc,
+};
+
+use runestick::{Any, Shared};
+
+use crate::{
+    frontend::{
+        headless::HeadlessFrontend, Frontend, InputKind, Support, Timeout, Validation,
+        INVALID_INPUT_MESSAGE,
+    },
+    markup,
+    wasm::rune_core::Output,
+};
+use bars::Bars;
+use error::EngineError;
+use history::History;
+use locale::{Locale, StringTable};
+use replay::{Replay, ReplayStep};
+use save::{SaveData, SaveError, SaveValue};
+use storage::{MemoryStorage, SaveStorage, AUTOSAVE_SLOT, QUICKSAVE_SLOT};
+
+pub const MODULE_NAME: &str = "Engine";
+/// `support` is what the frontend the game will be displayed on can do, which scripts can check
+/// with `Engine::Support`.
+pub fn create_module(support: Support) -> Result<runestick::Module, runestick::ContextError> {
+    let mut module = runestick::Module::new(&[MODULE_NAME]);
+    register(&mut module)?;
+    module.function(&["Support", "image"], move || support.image)?;
+    Ok(module)
+}
+
+pub fn register(module: &mut runestick::Module) -> Result<(), runestick::ContextError> {
+    Button::register(module)?;
+    Locale::register(module)?;
+    State::register(module)?;
+    Ok(())
+}
+
+#[derive(Debug, Any)]
+pub struct Scenes {
+    scenes: HashMap<String, Scene>,
+}
+impl Default for Scenes {
+    fn default() -> Self {
+        Self {
+            scenes: HashMap::with_capacity(64),
+        }
+    }
+}
+#[derive(Debug, Any)]
+pub struct Scene {
+    /// Shared so that it can be called without holding onto the scenes, which it may add to
+    display_callback: Rc<runestick::Function>,
+}
+impl Scene {
+    pub fn new(display_callback: runestick::Function) -> Self {
+        Self {
+            display_callback: Rc::new(display_callback),
+        }
+    }
+}
+
+#[derive(Debug, Any)]
+pub struct Button {
+    /// The text displayed for the button
+    pub text: String,
+    /// Data that should be used when it is activated
+    pub on_activate_data: runestick::Value,
+}
+impl Button {
+    pub fn new(text: String, on_activate_data: runestick::Value) -> Self {
+        Self {
+            text,
+            on_activate_data,
+        }
+    }
+
+    pub fn register(module: &mut runestick::Module) -> Result<(), runestick::ContextError> {
+        module.ty::<Self>()?;
+        module.function(&["Button", "new"], Self::new)?;
+        Ok(())
+    }
+}
+
+/// The scene transition waiting to be run.
+/// `State::goto` only queues the transition, and the scene is entered by `run` once the script
+/// has returned, rather than from inside of it. This keeps the Rust and Rune stacks from growing
+/// with every scene that is entered.
+/// Only the name is queued, as the state holds onto the transitions, and holding it back would
+/// keep both alive forever.
+#[derive(Debug, Clone, Default)]
+pub struct Transitions {
+    next: Rc<RefCell<Option<String>>>,
+    /// The scene that was entered last
+    current: Rc<RefCell<Option<String>>>,
+}
+impl Transitions {
+    fn push(&self, scene_name: String) {
+        *self.next.borrow_mut() = Some(scene_name);
+    }
+
+    /// Forget the queued transition without entering it.
+    fn clear(&self) {
+        self.next.borrow_mut().take();
+    }
+
+    /// The scene that the game is in, for the host. Scripts have `State::current_scene`.
+    pub fn current_scene(&self) -> Option<String> {
+        self.current.borrow().clone()
+    }
+
+    /// Enter queued scenes with the state until there are none left, stopping at the first
+    /// error. This should be called after anything that runs a script which may call
+    /// `State::goto`.
+    pub fn run(&self, state: &State) -> Result<(), EngineError> {
+        loop {
+            // Take it out in its own statement so that the borrow is over before the scene runs
+            let next = self.next.borrow_mut().take();
+            match next {
+                Some(scene_name) => {
+                    *self.current.borrow_mut() = Some(scene_name.clone());
+                    state.clone().enter(scene_name)?
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Any)]
+pub struct State {
+    pub current_scene: Option<String>,
+    pub scenes: Shared<Scenes>,
+    pub bars: Shared<Bars>,
+    /// The scenes that were entered, for going back to them
+    pub history: Shared<History>,
+    /// Everything the player has done, for reproducing the session
+    pub replay: Shared<Replay>,
+    pub info: Shared<runestick::Object>,
+    /// The string tables, and the language that text is shown in
+    pub locale: Locale,
+    /// What the game is being displayed with
+    pub frontend: Rc<dyn Frontend>,
+    /// Where save slots are kept
+    pub storage: Rc<dyn SaveStorage>,
+    /// The compiled scripts, which are needed to find the types of values being loaded
+    pub unit: Arc<runestick::Unit>,
+    /// Where the scripts print to, and the engine reports what shouldn't stop the game
+    pub(crate) output: Output,
+    /// Scene transitions that have yet to be run
+    pub transitions: Transitions,
+}
+impl State {
+    pub fn new(
+        frontend: Rc<dyn Frontend>,
+        storage: Rc<dyn SaveStorage>,
+        unit: Arc<runestick::Unit>,
+    ) -> Self {
+        State {
+            current_scene: None,
+            scenes: Shared::new(Scenes::default()),
+            bars: Shared::new(Bars::default()),
+            history: Shared::new(History::default()),
+            replay: Shared::new(Replay::default()),
+            info: Shared::new(runestick::Object::with_capacity(64)),
+            locale: Locale::default(),
+            frontend,
+            storage,
+            unit,
+            output: Output::default(),
+            transitions: Transitions::default(),
+        }
+    }
+
+    pub fn info(&self) -> Shared<runestick::Object> {
+        self.info.clone()
+    }
+
+    // TODO: mess with this so that you can return the old object
+    /// Overwrite the info
+    pub fn overwrite_info(&mut self, object: runestick::Object) -> Result<(), runestick::Panic> {
+        *self.info.borrow_mut().map_err(runestick::Panic::custom)? = object;
+        Ok(())
+    }
+
+    /// Queue entering the scene. It is entered once the currently running script returns.
+    pub fn goto(&self, scene_name: String) {
+        self.transitions.push(scene_name);
+    }
+
+    /// Actually enter the scene, running its display callback.
+    fn enter(mut self, scene_name: String) -> Result<(), EngineError> {
+        // Get the scene's callback out in its own statement, so that the scenes aren't borrowed
+        // while it runs and it can add more. This is done before anything is recorded, so that
+        // an unknown scene doesn't end up in the autosave.
+        let display_callback = self
+            .scenes
+            .borrow_ref()?
+            .scenes
+            .get(&scene_name)
+            .map(|scene| scene.display_callback.clone())
+            .ok_or_else(|| EngineError::UnknownScene(scene_name.clone()))?;
+
+        self.current_scene = Some(scene_name.clone());
+        // A failed autosave shouldn't stop the game, so it is only reported in the output.
+        if let Err(err) = self.save_to(AUTOSAVE_SLOT) {
+            self.output
+                .write_line(&format!("[Aleph] Autosave failed: {}", err));
+        }
+        display_callback
+            .call::<_, ()>((self,))
+            .map_err(|err| EngineError::script(Some(scene_name), err))
+    }
+
+    /// Remember the current scene and a copy of the info before a choice, so that it can be gone
+    /// back to. Info that can't be copied, such as a function, means that the history is
+    /// forgotten instead, and the game carries on without being able to go back past this choice.
+    fn record_history(&self) -> Result<(), EngineError> {
+        let entry = SaveData::new(self.current_scene.clone(), &*self.info.borrow_ref()?);
+        let mut history = self.history.borrow_mut()?;
+        match entry {
+            Ok(entry) => history.push(entry),
+            Err(err) => {
+                self.output
+                    .write_line(&format!("[Aleph] History was cleared: {}", err));
+                history.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// Restore the info and scene from a history entry, queueing entering the scene again.
+    fn restore(&self, entry: Option<SaveData>) -> Result<bool, EngineError> {
+        let entry = match entry {
+            Some(entry) => entry,
+            None => return Ok(false),
+        };
+        *self.info.borrow_mut()? = entry.load_info(&self.unit)?;
+        if let Some(scene_name) = entry.scene {
+            self.goto(scene_name);
+        }
+        Ok(true)
+    }
+
+    /// Undo the last choice, restoring the info from before it and queueing entering the scene
+    /// it was made in again. Returns whether there was a choice to undo.
+    fn undo_choice(&self) -> Result<bool, EngineError> {
+        // Take it out in its own statement so that the history isn't borrowed when entering
+        let entry = self.history.borrow_mut()?.pop();
+        self.restore(entry)
+    }
+
+    /// Go back to the previous scene, with the info as it was before the choice that left it.
+    /// Returns whether there was a previous scene to go back to.
+    fn go_back(&self) -> Result<bool, EngineError> {
+        let entry = self
+            .history
+            .borrow_mut()?
+            .pop_previous(self.current_scene.as_deref());
+        self.restore(entry)
+    }
+
+    /// Go back to the previous scene, with the info as it was before the choice that left it.
+    /// This undoes every choice made in this scene, and the one that led to it. Returns false,
+    /// without doing anything, if there is no previous scene.
+    pub fn back(&self) -> Result<bool, runestick::Panic> {
+        self.go_back().map_err(runestick::Panic::custom)
+    }
+
+    pub fn can_go_back(&self) -> Result<bool, runestick::Panic> {
+        Ok(self
+            .history
+            .borrow_ref()
+            .map_err(runestick::Panic::custom)?
+            .can_go_back(self.current_scene.as_deref()))
+    }
+
+    /// Change how many choices back the history goes. Defaults to `DEFAULT_HISTORY_LIMIT`.
+    pub fn set_history_limit(&self, limit: usize) -> Result<(), runestick::Panic> {
+        self.history
+            .borrow_mut()
+            .map_err(runestick::Panic::custom)?
+            .set_limit(limit);
+        Ok(())
+    }
+
+    pub fn locale(&self) -> Locale {
+        self.locale.clone()
+    }
+
+    /// The text for the key in the current language. See `Locale::tr`.
+    pub fn tr(&self, key: &str, args: runestick::Value) -> Result<String, runestick::Panic> {
+        self.locale.tr(key, args)
+    }
+
+    /// Switch the language that text is shown in, and queue entering the current scene again so
+    /// that it is shown in the new language.
+    fn change_language(&self, language: &str) -> Result<(), EngineError> {
+        self.locale
+            .set_language(language)
+            .map_err(EngineError::Config)?;
+        if let Some(scene_name) = self.current_scene.clone() {
+            self.goto(scene_name);
+        }
+        Ok(())
+    }
+
+    /// Show text in another language from now on, which must have a string table.
+    /// The current scene is entered again once the running script returns, to show it in the
+    /// new language.
+    pub fn set_language(&self, language: &str) -> Result<(), runestick::Panic> {
+        self.change_language(language)
+            .map_err(runestick::Panic::custom)
+    }
+
+    /// Add a bar that is displayed outside of the scenes, replacing any bar with the same name.
+    /// `render` is called with the info after every scene change and choice, and returns either
+    /// text or a vec of rows, where each row is a string or a `(label, value)` tuple.
+    pub fn add_bar(
+        &self,
+        name: String,
+        render: runestick::Function,
+    ) -> Result<(), runestick::Panic> {
+        self.bars
+            .borrow_mut()
+            .map_err(runestick::Panic::custom)?
+            .add(name, render);
+        Ok(())
+    }
+
+    pub fn remove_bar(&self, name: &str) -> Result<(), runestick::Panic> {
+        self.bars
+            .borrow_mut()
+            .map_err(runestick::Panic::custom)?
+            .remove(name);
+        Ok(())
+    }
+
+    /// Serialize the current scene and the info into json.
+    pub fn save(&self) -> Result<String, runestick::Panic> {
+        let info = self.info.borrow_ref().map_err(runestick::Panic::custom)?;
+        SaveData::new(self.current_scene.clone(), &info)
+            .and_then(|data| data.to_json())
+            .map_err(runestick::Panic::custom)
+    }
+
+    /// Replace the info with that from a save made by `save`, and re-enter the saved scene.
+    pub fn load(mut self, data: &str) -> Result<(), runestick::Panic> {
+        let data = SaveData::from_json(data).map_err(runestick::Panic::custom)?;
+        let info = data
+            .load_info(&self.unit)
+            .map_err(runestick::Panic::custom)?;
+        self.overwrite_info(info)?;
+
+        if let Some(scene_name) = data.scene {
+            self.goto(scene_name);
+        }
+        Ok(())
+    }
+
+    pub fn save_to(&self, slot: &str) -> Result<(), runestick::Panic> {
+        let data = self.save()?;
+        self.storage
+            .write(slot, &data)
+            .map_err(runestick::Panic::custom)
+    }
+
+    /// Check that the name can be used for a save slot. This is `Ok(())`, or `Err` with the
+    /// reason it can't, so that it can be returned straight from an input's validator.
+    pub fn check_slot(&self, slot: &str) -> runestick::Value {
+        let result = match storage::check_slot(slot) {
+            Ok(()) => Ok(runestick::Value::Unit),
+            Err(err) => Err(text_value(err.to_string())),
+        };
+        runestick::Value::Result(Shared::new(result))
+    }
+
+    pub fn load_from(self, slot: &str) -> Result<(), runestick::Panic> {
+        let data = self
+            .storage
+            .read(slot)
+            .map_err(runestick::Panic::custom)?
+            .ok_or_else(|| runestick::Panic::custom(SaveError::EmptySlot(slot.to_owned())))?;
+        self.load(&data)
+    }
+
+    /// The names of every slot with a save in it, including the quicksave and autosave.
+    pub fn list_saves(&self) -> Result<Vec<String>, runestick::Panic> {
+        self.storage.list().map_err(runestick::Panic::custom)
+    }
+
+    pub fn delete_save(&self, slot: &str) -> Result<(), runestick::Panic> {
+        self.storage.delete(slot).map_err(runestick::Panic::custom)
+    }
+
+    pub fn quicksave(&self) -> Result<(), runestick::Panic> {
+        self.save_to(QUICKSAVE_SLOT)
+    }
+
+    pub fn quickload(self) -> Result<(), runestick::Panic> {
+        self.load_from(QUICKSAVE_SLOT)
+    }
+
+    pub fn register(module: &mut runestick::Module) -> Result<(), runestick::ContextError> {
+        module.ty::<Self>()?;
+        module.inst_fn("ask_choice", Self::ask_choice)?;
+        module.inst_fn("ask_choice_timed", Self::ask_choice_timed)?;
+        module.inst_fn("ask_input", Self::ask_input)?;
+        module.inst_fn("ask_input_live", Self::ask_input_live)?;
+        module.inst_fn("ask_number", Self::ask_number)?;
+        module.inst_fn("ask_multiline", Self::ask_multiline)?;
+        module.inst_fn("ask_secret", Self::ask_secret)?;
+        module.inst_fn("ask_select", Self::ask_select)?;
+        // TODO: For some reason I can't register a getter that returns an `&mut Scenes`
+        module.getter("info", Self::info)?;
+        module.getter("locale", Self::locale)?;
+        module.inst_fn(
+            "add_scenes",
+            |state: &mut State,
+             new_scenes: Vec<(String, runestick::Function)>|
+             -> Result<(), runestick::Panic> {
+                let mut scenes = state
+                    .scenes
+                    .borrow_mut()
+                    .map_err(runestick::Panic::custom)?;
+                for (scene_name, display_callback) in new_scenes.into_iter() {
+                    scenes
+                        .scenes
+                        .insert(scene_name, Scene::new(display_callback));
+                }
+                Ok(())
+            },
+        )?;
+        module.inst_fn("goto", Self::goto)?;
+        module.inst_fn("back", Self::back)?;
+        module.inst_fn("can_go_back", Self::can_go_back)?;
+        module.inst_fn("set_history_limit", Self::set_history_limit)?;
+        module.inst_fn("add_bar", Self::add_bar)?;
+        module.inst_fn("remove_bar", Self::remove_bar)?;
+        module.inst_fn("overwrite_info", Self::overwrite_info)?;
+        module.inst_fn("save", Self::save)?;
+        module.inst_fn("load", Self::load)?;
+        module.inst_fn("save_to", Self::save_to)?;
+        module.inst_fn("load_from", Self::load_from)?;
+        module.inst_fn("check_slot", Self::check_slot)?;
+        module.inst_fn("list_saves", Self::list_saves)?;
+        module.inst_fn("delete_save", Self::delete_save)?;
+        module.inst_fn("quicksave", Self::quicksave)?;
+        module.inst_fn("quickload", Self::quickload)?;
+        // TODO: for wasm, at least, we could easily clone the TextElements and just return that
+        // through a getter. So: `state.text.set("blah");`, `state.text.append("bleh");`.
+        // but, this may not work on other backends so it should wait.
+        module.inst_fn("set_text", Self::set_text)?;
+        module.inst_fn("append_text", Self::append_text)?;
+        module.inst_fn("clear_text", Self::clear_text)?;
+        module.inst_fn("set_title", Self::set_title)?;
+        module.inst_fn("append_title", Self::append_title)?;
+        module.inst_fn("clear_title", Self::clear_title)?;
+        module.inst_fn("show_image", Self::show_image)?;
+        module.inst_fn("tr", Self::tr)?;
+        module.inst_fn("set_language", Self::set_language)?;
+
+        Ok(())
+    }
+
+    // TODO: maybe track that we are already asking a choice, so that we can't display > 1?
+    pub fn ask_choice(
+        &mut self,
+        buttons: Vec<Button>,
+        callback: runestick::Function,
+    ) -> Result<(), runestick::Panic> {
+        let choices = buttons.iter().map(|button| button.text.clone()).collect();
+        let state = self.clone();
+        self.frontend
+            .ask_choice(
+                choices,
+                Box::new(move |index| {
+                    // Extract the button, throwing away all the other buttons.
+                    let mut buttons = buttons;
+                    let button = buttons.swap_remove(index);
+                    let step = ReplayStep::Choice {
+                        index,
+                        text: button.text,
+                    };
+                    let result = state.answer(step, &callback, button.on_activate_data);
+                    report_error(&*state.frontend, result)
+                }),
+            )
+            .map_err(runestick::Panic::custom)
+    }
+
+    /// Like `ask_choice`, but if the player doesn't pick a choice within `seconds`, the callback
+    /// is called with `default_value` instead. The time left is shown to the player.
+    pub fn ask_choice_timed(
+        &mut self,
+        buttons: Vec<Button>,
+        seconds: runestick::Value,
+        default_value: runestick::Value,
+        callback: runestick::Function,
+    ) -> Result<(), runestick::Panic> {
+        let seconds = match seconds {
+            runestick::Value::Integer(seconds) => seconds as f64,
+            runestick::Value::Float(seconds) => seconds,
+            _ => return Err(runestick::Panic::custom("Expected a number of seconds")),
+        };
+        let choices = buttons.iter().map(|button| button.text.clone()).collect();
+        // Only one of the two will ever be called, but both need the callback
+        let callback = Rc::new(callback);
+        let timeout_callback = callback.clone();
+        let state = self.clone();
+        let timeout_state = self.clone();
+        self.frontend
+            .ask_choice_timed(
+                choices,
+                Timeout {
+                    seconds: seconds.max(0.0),
+                    callback: Box::new(move || {
+                        let result = timeout_state.answer(
+                            ReplayStep::Timeout,
+                            &timeout_callback,
+                            default_value,
+                        );
+                        report_error(&*timeout_state.frontend, result)
+                    }),
+                },
+                Box::new(move |index| {
+                    let mut buttons = buttons;
+                    let button = buttons.swap_remove(index);
+                    let step = ReplayStep::Choice {
+                        index,
+                        text: button.text,
+                    };
+                    let result = state.answer(step, &callback, button.on_activate_data);
+                    report_error(&*state.frontend, result)
+                }),
+            )
+            .map_err(runestick::Panic::custom)
+    }
+
+    /// Record what the player did, and the game as it was so that it can be undone, pass the
+    /// value it gave to the script's callback, and then enter whatever scene the callback went to.
+    fn answer(
+        &self,
+        step: ReplayStep,
+        callback: &runestick::Function,
+        value: runestick::Value,
+    ) -> Result<(), EngineError> {
+        self.replay.borrow_mut()?.push(step);
+        self.record_history()?;
+        callback
+            .call::<_, runestick::Value>((value,))
+            .map_err(|err| EngineError::script(self.current_scene.clone(), err))?;
+        self.transitions.run(self)?;
+        refresh_bars(&self.bars, &self.info, &*self.frontend)
+    }
+
+    /// Takes the default text, a function to check if the input is valid, and a function that is
+    /// given the input once it is. The validator returns `true` (or `()`, or `Ok(..)`) to accept
+    /// the input, and `false`, a message, or `Err(message)` to reject it. The message is shown to
+    /// the player.
+    pub fn ask_input(
+        &mut self,
+        default_text: String,
+        validator: runestick::Function,
+        callback: runestick::Function,
+    ) -> Result<(), runestick::Panic> {
+        self.ask_input_with(
+            default_text,
+            InputKind::Line,
+            Some(validator),
+            false,
+            callback,
+            |text| Ok(text_value(text)),
+        )
+    }
+
+    /// Like `ask_input`, but the input is also checked as the player types, on backends that can,
+    /// so that the message is shown straight away.
+    pub fn ask_input_live(
+        &mut self,
+        default_text: String,
+        validator: runestick::Function,
+        callback: runestick::Function,
+    ) -> Result<(), runestick::Panic> {
+        self.ask_input_with(
+            default_text,
+            InputKind::Line,
+            Some(validator),
+            true,
+            callback,
+            |text| Ok(text_value(text)),
+        )
+    }
+
+    /// Ask for a number from `min` to `max`, starting out at `default`. If all three are
+    /// integers then only whole numbers are accepted and the callback is given an integer,
+    /// otherwise it is given a float.
+    pub fn ask_number(
+        &mut self,
+        min: runestick::Value,
+        max: runestick::Value,
+        default: runestick::Value,
+        callback: runestick::Function,
+    ) -> Result<(), runestick::Panic> {
+        use runestick::Value;
+
+        let integer = [&min, &max, &default]
+            .iter()
+            .all(|value| matches!(value, Value::Integer(_)));
+        let number = |value: &Value| match value {
+            Value::Integer(value) => Ok(*value as f64),
+            Value::Float(value) => Ok(*value),
+            _ => Err(runestick::Panic::custom(
+                "Expected the minimum, maximum and default to be numbers",
+            )),
+        };
+        let (min, max) = (number(&min)?, number(&max)?);
+        if min > max {
+            return Err(runestick::Panic::custom(format!(
+                "The minimum {} is more than the maximum {}",
+                min, max
+            )));
+        }
+        number(&default)?;
+        let default_text = bars::display_value(&default).map_err(runestick::Panic::custom)?;
+        let kind = InputKind::Number { min, max, integer };
+        if let Err(message) = kind.check(&default_text) {
+            return Err(runestick::Panic::custom(format!(
+                "The default {} can't be entered: {}",
+                default_text, message
+            )));
+        }
+        self.ask_input_with(default_text, kind, None, false, callback, move |text| {
+            // `InputKind::check` has already made sure that it parses, but this passes on the
+            // error rather than making up a number if it somehow doesn't
+            let text = text.trim();
+            let value = if integer {
+                text.parse()
+                    .map(Value::Integer)
+                    .map_err(|err| err.to_string())
+            } else {
+                text.parse()
+                    .map(Value::Float)
+                    .map_err(|err| err.to_string())
+            };
+            value.map_err(|err| format!("Expected a number, but got '{}': {}", text, err))
+        })
+    }
+
+    /// Ask for text that may span several lines, starting out with the default text.
+    pub fn ask_multiline(
+        &mut self,
+        default_text: String,
+        callback: runestick::Function,
+    ) -> Result<(), runestick::Panic> {
+        self.ask_input_with(
+            default_text,
+            InputKind::Multiline,
+            None,
+            false,
+            callback,
+            |text| Ok(text_value(text)),
+        )
+    }
+
+    /// Ask for a line of text which is hidden as it is typed, such as the password to a door.
+    /// Note that it is still recorded in replays like any other input.
+    pub fn ask_secret(&mut self, callback: runestick::Function) -> Result<(), runestick::Panic> {
+        self.ask_input_with(
+            String::new(),
+            InputKind::Secret,
+            None,
+            false,
+            callback,
+            |text| Ok(text_value(text)),
+        )
+    }
+
+    /// Ask the player to pick one of the options, such as from a dropdown, starting out on the
+    /// first. The options are shown as text, and the callback is given the option that was picked.
+    pub fn ask_select(
+        &mut self,
+        options: Vec<runestick::Value>,
+        callback: runestick::Function,
+    ) -> Result<(), runestick::Panic> {
+        let labels = options
+            .iter()
+            .map(bars::display_value)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(runestick::Panic::custom)?;
+        let default_text = labels
+            .first()
+            .cloned()
+            .ok_or_else(|| runestick::Panic::custom("Expected at least one option"))?;
+        // The answer is the option's text, so each option has to be told apart by it
+        for (index, label) in labels.iter().enumerate() {
+            if labels[..index].contains(label) {
+                return Err(runestick::Panic::custom(format!(
+                    "There is more than one option shown as '{}'",
+                    label
+                )));
+            }
+        }
+        let picked_labels = labels.clone();
+        self.ask_input_with(
+            default_text,
+            InputKind::Select(labels),
+            None,
+            false,
+            callback,
+            move |text| {
+                let index = picked_labels
+                    .iter()
+                    .position(|label| *label == text)
+                    .ok_or_else(|| format!("'{}' isn't one of the options", text))?;
+                let mut options = options;
+                Ok(options.swap_remove(index))
+            },
+        )
+    }
+
+    /// Ask for input of the kind, which the callback is given once it is the right kind of
+    /// input and passes the validator, turned into a value by `to_value`. If that fails, then its
+    /// message is shown as an error in the script.
+    fn ask_input_with<F>(
+        &mut self,
+        default_text: String,
+        kind: InputKind,
+        validator: Option<runestick::Function>,
+        live: bool,
+        callback: runestick::Function,
+        to_value: F,
+    ) -> Result<(), runestick::Panic>
+    where
+        F: 'static + FnOnce(String) -> Result<runestick::Value, String>,
+    {
+        let validator_kind = kind.clone();
+        let validator_frontend = self.frontend.clone();
+        let validator_scene = self.current_scene.clone();
+        let state = self.clone();
+        self.frontend
+            .ask_input(
+                default_text.as_str(),
+                kind,
+                Box::new(move |text: String| {
+                    if let Err(message) = validator_kind.check(&text) {
+                        return Ok(Err(message));
+                    }
+                    let validator = match &validator {
+                        Some(validator) => validator,
+                        None => return Ok(Ok(())),
+                    };
+                    let result = validator
+                        .call::<_, runestick::Value>((text,))
+                        .map_err(|err| EngineError::script(validator_scene.clone(), err))
+                        .and_then(|value| {
+                            validation(value).map_err(|error| EngineError::Script {
+                                scene: validator_scene.clone(),
+                                error,
+                            })
+                        });
+                    report_error(&*validator_frontend, result)
+                }),
+                live,
+                Box::new(move |text: String| {
+                    let step = ReplayStep::Input { text: text.clone() };
+                    let result = to_value(text)
+                        .map_err(|error| EngineError::Script {
+                            scene: state.current_scene.clone(),
+                            error,
+                        })
+                        .and_then(|value| state.answer(step, &callback, value));
+                    report_error(&*state.frontend, result)
+                }),
+            )
+            .map_err(runestick::Panic::custom)
+    }
+
+    pub fn set_text(&self, text: &str) {
+        self.frontend.set_text(text);
+    }
+    pub fn append_text(&self, text: &str) {
+        self.frontend.append_text(text);
+    }
+    pub fn clear_text(&self) {
+        self.frontend.clear_text();
+    }
+
+    /// Show the image at the end of the text. Backends that can't display images show the alt
+    /// text instead, see `Engine::Support::image()`.
+    pub fn show_image(&self, path: &str, alt: &str) -> Result<(), runestick::Panic> {
+        let image = markup::image(path, alt)
+            .ok_or_else(|| runestick::Panic::custom(format!("Invalid image path '{}'", path)))?;
+        self.frontend.append_text(&image);
+        Ok(())
+    }
+
+    pub fn set_title(&self, text: &str) {
+        self.frontend.set_title(text);
+    }
+    pub fn append_title(&self, text: &str) {
+        self.frontend.append_title(text);
+    }
+    pub fn clear_title(&self) {
+        self.frontend.clear_title();
+    }
+}
+
+/// A handle to a running game, so that the host can act on it from outside of the scripts, such
+/// as undoing the last choice for the player.
+#[derive(Debug, Clone)]
+pub struct Handle {
+    state: State,
+}
+impl Handle {
+    pub fn new(state: State) -> Self {
+        Self { state }
+    }
+
+    /// Undo the last choice, restoring the info from before it and entering the scene it was made
+    /// in again. Returns whether there was anything to undo.
+    pub fn undo(&self) -> Result<bool, EngineError> {
+        let state = &self.state;
+        let result = state.undo_choice().and_then(|undone| {
+            if undone {
+                state.replay.borrow_mut()?.push(ReplayStep::Undo);
+            }
+            state.transitions.run(state)?;
+            refresh_bars(&state.bars, &state.info, &*state.frontend)?;
+            Ok(undone)
+        });
+        report_error(&*state.frontend, result)
+    }
+
+    /// What the frontend the game is displayed on can do.
+    pub fn support(&self) -> Support {
+        self.state.frontend.support()
+    }
+
+    /// Swap in newly compiled scripts and string tables, keeping the info, the language and the
+    /// scene the game is in.
+    /// The new `entry` is run on a state that isn't displayed anywhere and saves nowhere, just to
+    /// find the scenes and bars it adds, and anything new that it puts in the info. Then the
+    /// current scene is entered again with the new scripts.
+    /// If the new `entry` fails, the new scripts don't have the current scene, or the info can't
+    /// be carried over (such as if a struct it holds was removed), then the error is only
+    /// returned, and the game carries on as it was.
+    pub fn reload(
+        &mut self,
+        vm: runestick::Vm,
+        locales: Vec<StringTable>,
+    ) -> Result<(), EngineError> {
+        let unit = vm.unit().clone();
+        let mut fresh = State::new(
+            Rc::new(HeadlessFrontend::default()),
+            Rc::new(MemoryStorage::default()),
+            unit.clone(),
+        );
+        fresh.locale = Locale::new(locales.clone(), self.state.locale.language());
+        fresh.output = self.state.output.clone();
+        let scenes = fresh.scenes.clone();
+        let bars = fresh.bars.clone();
+        let fresh_info = fresh.info.clone();
+        let fresh_transitions = fresh.transitions.clone();
+        let result = vm.call(&["entry"], (fresh,));
+        // Whatever `entry` went to is never entered
+        fresh_transitions.clear();
+        result.map_err(|err| EngineError::script(None, err))?;
+
+        // Everything is checked before any of it is swapped in, so that a failure leaves the game
+        // as it was
+        let current_scene = self.state.transitions.current_scene();
+        if let Some(scene_name) = &current_scene {
+            if !scenes.borrow_ref()?.scenes.contains_key(scene_name) {
+                return Err(EngineError::UnknownScene(scene_name.clone()));
+            }
+        }
+        // Recreate the info so that any script-declared types in it are those of the new scripts
+        let mut info = SaveData::new(None, &*self.state.info.borrow_ref()?)?.load_info(&unit)?;
+        for (key, value) in fresh_info.borrow_ref()?.iter() {
+            if info.get(key).is_none() {
+                info.insert(key.clone(), value.clone());
+            }
+        }
+
+        *self.state.scenes.borrow_mut()? = std::mem::take(&mut *scenes.borrow_mut()?);
+        *self.state.bars.borrow_mut()? = std::mem::take(&mut *bars.borrow_mut()?);
+        *self.state.info.borrow_mut()? = info;
+        self.state.locale.set_tables(locales);
+        self.state.unit = unit;
+
+        let state = &self.state;
+        let result = match current_scene {
+            Some(scene_name) => {
+                state.goto(scene_name);
+                state.transitions.run(state)
+            }
+            None => Ok(()),
+        }
+        .and_then(|_| refresh_bars(&state.bars, &state.info, &*state.frontend));
+        report_error(&*state.frontend, result)
+    }
+
+    /// Show the game in another language, entering the current scene again in it.
+    /// This is what `state.set_language` does, for the host, such as for a language menu.
+    /// A language without a string table is only returned as an error, and changes nothing.
+    /// The change is recorded in the replay, as it changes what the player is shown.
+    pub fn set_language(&self, language: &str) -> Result<(), EngineError> {
+        let mut state = self.state.clone();
+        state.current_scene = state.transitions.current_scene();
+        state
+            .locale
+            .check_language(language)
+            .map_err(EngineError::Config)?;
+        let result = state.change_language(language).and_then(|_| {
+            state
+                .replay
+                .borrow_mut()?
+                .push(ReplayStep::Language(language.to_owned()));
+            state.transitions.run(&state)?;
+            refresh_bars(&state.bars, &state.info, &*state.frontend)
+        });
+        report_error(&*state.frontend, result)
+    }
+
+    /// Take everything the scripts printed since the last time this was called.
+    pub fn drain_output(&self) -> Option<String> {
+        self.state.output.drain()
+    }
+
+    pub(crate) fn output(&self) -> &Output {
+        &self.state.output
+    }
+
+    /// The language that text is being shown in, if there are any string tables.
+    pub fn language(&self) -> Option<String> {
+        self.state.locale.language()
+    }
+
+    /// The scene that the game is in, if it has entered one.
+    pub fn current_scene(&self) -> Option<String> {
+        self.state.transitions.current_scene()
+    }
+
+    /// A copy of the info, in the same form as it is saved.
+    pub fn info(&self) -> Result<BTreeMap<String, SaveValue>, EngineError> {
+        Ok(SaveData::new(None, &*self.state.info.borrow_ref()?)?.info)
+    }
+
+    /// Everything the player has done so far, which can be played back with
+    /// `Headless::play_replay`.
+    pub fn replay(&self) -> Result<Replay, EngineError> {
+        Ok(self.state.replay.borrow_ref()?.clone())
+    }
+}
+
+/// Text the player entered, as a value for a script.
+fn text_value(text: String) -> runestick::Value {
+    runestick::Value::String(Shared::new(text))
+}
+
+/// Turn what an input's validator returned into whether the input was accepted.
+fn validation(value: runestick::Value) -> Result<Validation, String> {
+    use runestick::Value;
+
+    let message = |value: &Value| {
+        bars::display_value(value).unwrap_or_else(|_| INVALID_INPUT_MESSAGE.to_owned())
+    };
+    Ok(match value {
+        Value::Bool(true) | Value::Unit => Ok(()),
+        Value::Bool(false) => Err(INVALID_INPUT_MESSAGE.to_owned()),
+        Value::String(_) | Value::StaticString(_) => Err(message(&value)),
+        Value::Result(result) => match &*result.borrow_ref().map_err(|err| err.to_string())? {
+            Ok(_) => Ok(()),
+            Err(value) => Err(message(value)),
+        },
+        value => {
+            let type_name = value
+                .type_info()
+                .map(|type_info| type_info.to_string())
+                .unwrap_or_else(|_| "unknown".to_owned());
+            return Err(format!(
+                "Expected the validator to return a bool, a message or a Result, but it returned '{}'",
+                type_name
+            ));
+        }
+    })
+}
+
+/// Re-render every bar from the info and display them.
+/// This is done once the scripts are done running after each choice or input, so that any scene
+/// changes have already happened.
+pub fn refresh_bars(
+    bars: &Shared<Bars>,
+    info: &Shared<runestick::Object>,
+    frontend: &dyn Frontend,
+) -> Result<(), EngineError> {
+    let rendered = bars.borrow_ref()?.render(info)?;
+    frontend.set_bars(&rendered);
+    Ok(())
+}
+
+/// Show the error to the player, if there was one, before handing the result back to the host.
+pub fn report_error<T>(
+    frontend: &dyn Frontend,
+    result: Result<T, EngineError>,
+) -> Result<T, EngineError> {
+    if let Err(err) = &result {
+        frontend.show_error(err);
+    }
+    result
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+use super::save::SaveError;
+
+/// The version of the replay format that we write.
+pub const REPLAY_VERSION: u32 = 2;
+
+/// One thing the player did.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReplayStep {
+    /// Picked the choice at `index`. The text is kept to notice if the choices have changed since.
+    Choice { index: usize, text: String },
+    /// Submitted the text to an input, after it was accepted by the validator.
+    Input { text: String },
+    /// Undid the last choice from the host, such as with `Headless::undo`.
+    Undo,
+    /// Let a timed choice run out of time, so that its default was used.
+    Timeout,
+    /// Switched the language from the host, such as with `Headless::set_language`. Switches made
+    /// by the scripts aren't recorded, as playing the steps back makes them again.
+    Language(String),
+}
+
+/// Everything the player did since the game started, in order. Playing the steps back on a fresh
+/// game reproduces the session, as long as the scripts and the saves it loaded are the same.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    /// The language the game started in, if it has string tables
+    #[serde(default)]
+    pub language: Option<String>,
+    pub steps: Vec<ReplayStep>,
+}
+impl Default for Replay {
+    fn default() -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            language: None,
+            steps: Vec::new(),
+        }
+    }
+}
+impl Replay {
+    pub fn push(&mut self, step: ReplayStep) {
+        self.steps.push(step);
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, SaveError> {
+        let replay: Self = serde_json::from_str(text)?;
+        if replay.version > REPLAY_VERSION {
+            return Err(SaveError::Json(format!(
+                "Replay is from version {}, but only up to version {} is supported",
+                replay.version, REPLAY_VERSION
+            )));
+        }
+        Ok(replay)
+    }
+
+    pub fn to_json(&self) -> Result<String, SaveError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
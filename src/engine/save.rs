@@ -0,0 +1,586 @@
+use std::collections::BTreeMap;
+
+use runestick::{Hash, Shared, Value};
+use serde::{Deserialize, Serialize};
+
+/// The version of the save format that we write.
+/// Saves from a newer version are refused, since we can't know what changed in them.
+pub const SAVE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveError {
+    /// A value could not be accessed, such as if it was already mutably borrowed.
+    Access(String),
+    /// A value of a type which can't be saved, such as a function.
+    Unsupported(String),
+    /// A float that is NaN or infinite, which json has no way of writing.
+    NonFinite(f64),
+    /// A value that contains itself, such as a vec that was pushed into itself.
+    Cycle,
+    /// The save could not be converted to or from json.
+    Json(String),
+    /// The save was made by a newer version of the engine.
+    NewerVersion(u32),
+    /// The save refers to a struct or enum which is not declared by the scripts.
+    UnknownType(String),
+    /// The slot name is not allowed, see `storage::check_slot`.
+    InvalidSlot(String),
+    /// There is no save in the slot.
+    EmptySlot(String),
+    /// The storage failed to read or write the save.
+    Storage(String),
+}
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Access(err) => write!(f, "Failed to access value: {}", err),
+            Self::Unsupported(type_name) => write!(f, "Can't save value of type '{}'", type_name),
+            Self::NonFinite(value) => {
+                write!(
+                    f,
+                    "Can't save '{}', only finite numbers can be saved",
+                    value
+                )
+            }
+            Self::Cycle => write!(f, "Can't save a value that contains itself"),
+            Self::Json(err) => write!(f, "Bad save data: {}", err),
+            Self::NewerVersion(version) => write!(
+                f,
+                "Save is from version {}, but only up to version {} is supported",
+                version, SAVE_VERSION
+            ),
+            Self::UnknownType(name) => write!(f, "Save refers to unknown type '{}'", name),
+            Self::InvalidSlot(slot) => write!(
+                f,
+                "Invalid save slot name '{}'. Only letters, numbers, spaces, '-' and '_' are allowed, without a space at the end, and it can't be a name Windows reserves like 'CON'",
+                slot
+            ),
+            Self::EmptySlot(slot) => write!(f, "There is no save in slot '{}'", slot),
+            Self::Storage(err) => write!(f, "Failed to access save storage: {}", err),
+        }
+    }
+}
+impl From<runestick::AccessError> for SaveError {
+    fn from(error: runestick::AccessError) -> Self {
+        Self::Access(error.to_string())
+    }
+}
+impl From<serde_json::Error> for SaveError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error.to_string())
+    }
+}
+
+/// Everything needed to resume a game.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u32,
+    /// The scene that the game was in, which is re-entered upon loading.
+    pub scene: Option<String>,
+    pub info: BTreeMap<String, SaveValue>,
+}
+impl SaveData {
+    pub fn new(scene: Option<String>, info: &runestick::Object) -> Result<Self, SaveError> {
+        Ok(Self {
+            version: SAVE_VERSION,
+            scene,
+            info: within(info, &mut Vec::new(), save_object)?,
+        })
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, SaveError> {
+        let data: Self = serde_json::from_str(text)?;
+        if data.version > SAVE_VERSION {
+            return Err(SaveError::NewerVersion(data.version));
+        }
+        Ok(data)
+    }
+
+    pub fn to_json(&self) -> Result<String, SaveError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Recreate the info object, looking up any script-declared types in the unit.
+    pub fn load_info(&self, unit: &runestick::Unit) -> Result<runestick::Object, SaveError> {
+        load_object(&self.info, unit)
+    }
+}
+
+/// A `runestick::Value` in a form that can be serialized.
+/// Structs and enum variants declared by scripts are stored by their full name (`SkinTone::Tan`),
+/// so that they can be found again even if the script was recompiled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SaveValue {
+    Unit,
+    Bool(bool),
+    Byte(u8),
+    Char(char),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Vec(Vec<SaveValue>),
+    Tuple(Vec<SaveValue>),
+    Object(BTreeMap<String, SaveValue>),
+    Option(Option<Box<SaveValue>>),
+    UnitStruct(String),
+    TupleStruct(String, Vec<SaveValue>),
+    Struct(String, BTreeMap<String, SaveValue>),
+    UnitVariant(String),
+    TupleVariant(String, Vec<SaveValue>),
+    StructVariant(String, BTreeMap<String, SaveValue>),
+}
+impl SaveValue {
+    pub fn from_value(value: &Value) -> Result<Self, SaveError> {
+        save_value(value, &mut Vec::new())
+    }
+
+    /// Turn this back into a value, looking up any script-declared types in the unit.
+    pub fn to_value(&self, unit: &runestick::Unit) -> Result<Value, SaveError> {
+        Ok(match self {
+            Self::Unit => Value::Unit,
+            Self::Bool(value) => Value::Bool(*value),
+            Self::Byte(value) => Value::Byte(*value),
+            Self::Char(value) => Value::Char(*value),
+            Self::Integer(value) => Value::Integer(*value),
+            Self::Float(value) => Value::Float(*value),
+            Self::String(value) => Value::String(Shared::new(value.clone())),
+            Self::Vec(values) => Value::Vec(Shared::new(runestick::Vec::from(load_values(
+                values, unit,
+            )?))),
+            Self::Tuple(values) => Value::Tuple(Shared::new(runestick::Tuple::from(load_values(
+                values, unit,
+            )?))),
+            Self::Object(object) => Value::Object(Shared::new(load_object(object, unit)?)),
+            Self::Option(value) => Value::Option(Shared::new(match value {
+                Some(value) => Some(value.to_value(unit)?),
+                None => None,
+            })),
+            Self::UnitStruct(name) => Value::UnitStruct(Shared::new(runestick::UnitStruct {
+                rtti: lookup_rtti(name, unit)?,
+            })),
+            Self::TupleStruct(name, values) => {
+                Value::TupleStruct(Shared::new(runestick::TupleStruct {
+                    rtti: lookup_rtti(name, unit)?,
+                    data: runestick::Tuple::from(load_values(values, unit)?),
+                }))
+            }
+            Self::Struct(name, object) => Value::Struct(Shared::new(runestick::Struct {
+                rtti: lookup_rtti(name, unit)?,
+                data: load_object(object, unit)?,
+            })),
+            Self::UnitVariant(name) => Value::UnitVariant(Shared::new(runestick::UnitVariant {
+                rtti: lookup_variant_rtti(name, unit)?,
+            })),
+            Self::TupleVariant(name, values) => {
+                Value::TupleVariant(Shared::new(runestick::TupleVariant {
+                    rtti: lookup_variant_rtti(name, unit)?,
+                    data: runestick::Tuple::from(load_values(values, unit)?),
+                }))
+            }
+            Self::StructVariant(name, object) => {
+                Value::StructVariant(Shared::new(runestick::StructVariant {
+                    rtti: lookup_variant_rtti(name, unit)?,
+                    data: load_object(object, unit)?,
+                }))
+            }
+        })
+    }
+}
+
+/// Written like the Rune literal that would make the value, such as `"text"` or `Some(5)`.
+impl std::fmt::Display for SaveValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn list(f: &mut std::fmt::Formatter<'_>, values: &[SaveValue]) -> std::fmt::Result {
+            for (index, value) in values.iter().enumerate() {
+                if index != 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", value)?;
+            }
+            Ok(())
+        }
+        fn fields(
+            f: &mut std::fmt::Formatter<'_>,
+            object: &BTreeMap<String, SaveValue>,
+        ) -> std::fmt::Result {
+            write!(f, "{{")?;
+            for (index, (key, value)) in object.iter().enumerate() {
+                if index != 0 {
+                    write!(f, ",")?;
+                }
+                write!(f, " {}: {}", key, value)?;
+            }
+            write!(f, " }}")
+        }
+
+        match self {
+            Self::Unit => write!(f, "()"),
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Byte(value) => write!(f, "b'{}'", std::ascii::escape_default(*value)),
+            Self::Char(value) => write!(f, "{:?}", value),
+            Self::Integer(value) => write!(f, "{}", value),
+            Self::Float(value) => write!(f, "{:?}", value),
+            Self::String(value) => write!(f, "{:?}", value),
+            Self::Vec(values) => {
+                write!(f, "[")?;
+                list(f, values)?;
+                write!(f, "]")
+            }
+            Self::Tuple(values) => {
+                write!(f, "(")?;
+                list(f, values)?;
+                write!(f, ")")
+            }
+            Self::Object(object) => {
+                write!(f, "#")?;
+                fields(f, object)
+            }
+            Self::Option(Some(value)) => write!(f, "Some({})", value),
+            Self::Option(None) => write!(f, "None"),
+            Self::UnitStruct(name) | Self::UnitVariant(name) => write!(f, "{}", name),
+            Self::TupleStruct(name, values) | Self::TupleVariant(name, values) => {
+                write!(f, "{}(", name)?;
+                list(f, values)?;
+                write!(f, ")")
+            }
+            Self::Struct(name, object) | Self::StructVariant(name, object) => {
+                write!(f, "{} ", name)?;
+                fields(f, object)
+            }
+        }
+    }
+}
+
+/// `path` holds the addresses of the values that this one is inside of, so that a value which
+/// contains itself is refused rather than being saved forever.
+fn save_value(value: &Value, path: &mut Vec<usize>) -> Result<SaveValue, SaveError> {
+    Ok(match value {
+        Value::Unit => SaveValue::Unit,
+        Value::Bool(value) => SaveValue::Bool(*value),
+        Value::Byte(value) => SaveValue::Byte(*value),
+        Value::Char(value) => SaveValue::Char(*value),
+        Value::Integer(value) => SaveValue::Integer(*value),
+        Value::Float(value) if !value.is_finite() => return Err(SaveError::NonFinite(*value)),
+        Value::Float(value) => SaveValue::Float(*value),
+        Value::StaticString(value) => SaveValue::String(value.as_str().to_owned()),
+        Value::String(value) => SaveValue::String(value.borrow_ref()?.clone()),
+        Value::Vec(value) => SaveValue::Vec(within(&*value.borrow_ref()?, path, |value, path| {
+            save_values(value.iter(), path)
+        })?),
+        Value::Tuple(value) => {
+            SaveValue::Tuple(within(&*value.borrow_ref()?, path, |value, path| {
+                save_values(value.iter(), path)
+            })?)
+        }
+        Value::Object(value) => {
+            SaveValue::Object(within(&*value.borrow_ref()?, path, save_object)?)
+        }
+        Value::Option(value) => {
+            SaveValue::Option(within(&*value.borrow_ref()?, path, |value, path| {
+                Ok(match value {
+                    Some(value) => Some(Box::new(save_value(value, path)?)),
+                    None => None,
+                })
+            })?)
+        }
+        Value::UnitStruct(value) => {
+            SaveValue::UnitStruct(value.borrow_ref()?.rtti.item.to_string())
+        }
+        Value::TupleStruct(value) => within(&*value.borrow_ref()?, path, |value, path| {
+            Ok(SaveValue::TupleStruct(
+                value.rtti.item.to_string(),
+                save_values(value.data.iter(), path)?,
+            ))
+        })?,
+        Value::Struct(value) => within(&*value.borrow_ref()?, path, |value, path| {
+            Ok(SaveValue::Struct(
+                value.rtti.item.to_string(),
+                save_object(&value.data, path)?,
+            ))
+        })?,
+        Value::UnitVariant(value) => {
+            SaveValue::UnitVariant(value.borrow_ref()?.rtti.item.to_string())
+        }
+        Value::TupleVariant(value) => within(&*value.borrow_ref()?, path, |value, path| {
+            Ok(SaveValue::TupleVariant(
+                value.rtti.item.to_string(),
+                save_values(value.data.iter(), path)?,
+            ))
+        })?,
+        Value::StructVariant(value) => within(&*value.borrow_ref()?, path, |value, path| {
+            Ok(SaveValue::StructVariant(
+                value.rtti.item.to_string(),
+                save_object(&value.data, path)?,
+            ))
+        })?,
+        value => {
+            let type_name = value
+                .type_info()
+                .map(|type_info| type_info.to_string())
+                .unwrap_or_else(|_| "unknown".to_owned());
+            return Err(SaveError::Unsupported(type_name));
+        }
+    })
+}
+
+/// Save what is inside of a shared value, which is a cycle if it is already being saved further
+/// up the path. The same value appearing twice side by side is fine, and is saved twice.
+fn within<T, R>(
+    value: &T,
+    path: &mut Vec<usize>,
+    save: impl FnOnce(&T, &mut Vec<usize>) -> Result<R, SaveError>,
+) -> Result<R, SaveError> {
+    let address = value as *const T as usize;
+    if path.contains(&address) {
+        return Err(SaveError::Cycle);
+    }
+    path.push(address);
+    let result = save(value, path);
+    path.pop();
+    result
+}
+
+fn save_values<'a>(
+    values: impl Iterator<Item = &'a Value>,
+    path: &mut Vec<usize>,
+) -> Result<Vec<SaveValue>, SaveError> {
+    values.map(|value| save_value(value, path)).collect()
+}
+
+fn save_object(
+    object: &runestick::Object,
+    path: &mut Vec<usize>,
+) -> Result<BTreeMap<String, SaveValue>, SaveError> {
+    object
+        .iter()
+        .map(|(key, value)| Ok((key.clone(), save_value(value, path)?)))
+        .collect()
+}
+
+fn load_values(values: &[SaveValue], unit: &runestick::Unit) -> Result<Vec<Value>, SaveError> {
+    values.iter().map(|value| value.to_value(unit)).collect()
+}
+
+fn load_object(
+    object: &BTreeMap<String, SaveValue>,
+    unit: &runestick::Unit,
+) -> Result<runestick::Object, SaveError> {
+    let mut result = runestick::Object::with_capacity(object.len());
+    for (key, value) in object {
+        result.insert(key.clone(), value.to_value(unit)?);
+    }
+    Ok(result)
+}
+
+/// Get the hash of an item from its full name, such as `SkinTone::Tan`.
+fn item_hash(name: &str) -> Hash {
+    Hash::type_hash(name.split("::"))
+}
+
+fn lookup_rtti(
+    name: &str,
+    unit: &runestick::Unit,
+) -> Result<std::sync::Arc<runestick::Rtti>, SaveError> {
+    unit.lookup_rtti(item_hash(name))
+        .cloned()
+        .ok_or_else(|| SaveError::UnknownType(name.to_owned()))
+}
+
+fn lookup_variant_rtti(
+    name: &str,
+    unit: &runestick::Unit,
+) -> Result<std::sync::Arc<runestick::VariantRtti>, SaveError> {
+    unit.lookup_variant_rtti(item_hash(name))
+        .cloned()
+        .ok_or_else(|| SaveError::UnknownType(name.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compile the code, for the types it declares.
+    fn unit(code: &str) -> runestick::Unit {
+        let mut sources = rune::Sources::new();
+        sources.insert(runestick::Source::new("test", code));
+        let context = runestick::Context::with_config(false).unwrap();
+        rune::load_sources(
+            &context,
+            &rune::Options::default(),
+            &mut sources,
+            &mut rune::Errors::new(),
+            &mut rune::Warnings::new(),
+        )
+        .unwrap()
+    }
+
+    fn object(pairs: &[(&str, SaveValue)]) -> BTreeMap<String, SaveValue> {
+        pairs
+            .iter()
+            .map(|(key, value)| ((*key).to_owned(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip_values() {
+        let unit = unit(
+            "struct Player { name, race }
+            struct Point(x, y);
+            struct Marker;
+            enum Race { Human, Sylph(age), Elf { home } }",
+        );
+        let values = vec![
+            SaveValue::Unit,
+            SaveValue::Bool(true),
+            SaveValue::Byte(b'a'),
+            SaveValue::Char('é'),
+            SaveValue::Integer(-5),
+            SaveValue::Float(1.5),
+            SaveValue::String("Sam".to_owned()),
+            SaveValue::Vec(vec![SaveValue::Integer(1), SaveValue::Integer(2)]),
+            SaveValue::Tuple(vec![SaveValue::Unit, SaveValue::Bool(false)]),
+            SaveValue::Object(object(&[("gold", SaveValue::Integer(3))])),
+            SaveValue::Option(Some(Box::new(SaveValue::Integer(5)))),
+            SaveValue::Option(None),
+            SaveValue::UnitStruct("Marker".to_owned()),
+            SaveValue::TupleStruct(
+                "Point".to_owned(),
+                vec![SaveValue::Integer(1), SaveValue::Integer(2)],
+            ),
+            SaveValue::Struct(
+                "Player".to_owned(),
+                object(&[
+                    ("name", SaveValue::String("Sam".to_owned())),
+                    ("race", SaveValue::UnitVariant("Race::Human".to_owned())),
+                ]),
+            ),
+            SaveValue::TupleVariant("Race::Sylph".to_owned(), vec![SaveValue::Integer(30)]),
+            SaveValue::StructVariant(
+                "Race::Elf".to_owned(),
+                object(&[("home", SaveValue::String("Aurum".to_owned()))]),
+            ),
+        ];
+        for value in values {
+            let loaded = value.to_value(&unit).unwrap();
+            assert_eq!(SaveValue::from_value(&loaded), Ok(value));
+        }
+    }
+
+    #[test]
+    fn unknown_type() {
+        let unit = unit("struct Player { name }");
+        assert_eq!(
+            SaveValue::UnitStruct("Missing".to_owned())
+                .to_value(&unit)
+                .map(|_| ()),
+            Err(SaveError::UnknownType("Missing".to_owned()))
+        );
+        assert_eq!(
+            SaveValue::UnitVariant("Race::Human".to_owned())
+                .to_value(&unit)
+                .map(|_| ()),
+            Err(SaveError::UnknownType("Race::Human".to_owned()))
+        );
+    }
+
+    #[test]
+    fn unsaveable_values() {
+        assert_eq!(
+            SaveValue::from_value(&Value::Float(f64::INFINITY)),
+            Err(SaveError::NonFinite(f64::INFINITY))
+        );
+        assert!(matches!(
+            SaveValue::from_value(&Value::Float(f64::NAN)),
+            Err(SaveError::NonFinite(_))
+        ));
+
+        // The same vec twice is fine, but not a vec inside of itself
+        let inner = Value::Vec(Shared::new(runestick::Vec::from(vec![Value::Integer(1)])));
+        let twice = Value::Tuple(Shared::new(runestick::Tuple::from(vec![
+            inner.clone(),
+            inner.clone(),
+        ])));
+        assert!(SaveValue::from_value(&twice).is_ok());
+        if let Value::Vec(vec) = &inner {
+            vec.borrow_mut().unwrap().push(twice.clone());
+        }
+        assert_eq!(SaveValue::from_value(&twice), Err(SaveError::Cycle));
+
+        let object = Value::Object(Shared::new(runestick::Object::new()));
+        if let Value::Object(shared) = &object {
+            shared
+                .borrow_mut()
+                .unwrap()
+                .insert("self".to_owned(), object.clone());
+        }
+        assert_eq!(SaveValue::from_value(&object), Err(SaveError::Cycle));
+    }
+
+    #[test]
+    fn round_trip_json() {
+        let mut info = runestick::Object::new();
+        info.insert("gold".to_owned(), Value::Integer(10));
+        info.insert(
+            "name".to_owned(),
+            Value::String(Shared::new("Sam".to_owned())),
+        );
+        let data = SaveData::new(Some("town".to_owned()), &info).unwrap();
+        assert_eq!(data.version, SAVE_VERSION);
+        assert_eq!(
+            data.info,
+            object(&[
+                ("gold", SaveValue::Integer(10)),
+                ("name", SaveValue::String("Sam".to_owned())),
+            ])
+        );
+        let loaded = SaveData::from_json(&data.to_json().unwrap()).unwrap();
+        assert_eq!(loaded, data);
+
+        let info = loaded.load_info(&unit("")).unwrap();
+        assert!(matches!(info.get("gold"), Some(Value::Integer(10))));
+    }
+
+    #[test]
+    fn reject_versions() {
+        let newer = SaveData {
+            version: SAVE_VERSION + 1,
+            scene: None,
+            info: BTreeMap::new(),
+        };
+        assert_eq!(
+            SaveData::from_json(&newer.to_json().unwrap()),
+            Err(SaveError::NewerVersion(SAVE_VERSION + 1))
+        );
+        let older = SaveData {
+            version: SAVE_VERSION - 1,
+            ..newer
+        };
+        assert_eq!(SaveData::from_json(&older.to_json().unwrap()), Ok(older));
+        assert!(matches!(
+            SaveData::from_json("{\"scene\": null}"),
+            Err(SaveError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn display() {
+        let player = SaveValue::Struct(
+            "Player".to_owned(),
+            object(&[
+                ("name", SaveValue::String("Sam".to_owned())),
+                (
+                    "gold",
+                    SaveValue::Option(Some(Box::new(SaveValue::Integer(5)))),
+                ),
+            ]),
+        );
+        assert_eq!(
+            player.to_string(),
+            "Player { gold: Some(5), name: \"Sam\" }"
+        );
+        assert_eq!(
+            SaveValue::Vec(vec![SaveValue::Float(1.0), SaveValue::Char('a')]).to_string(),
+            "[1.0, 'a']"
+        );
+    }
+}
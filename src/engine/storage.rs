@@ -0,0 +1,235 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use super::save::SaveError;
+
+/// The slot that `State::quicksave` and `State::quickload` use.
+pub const QUICKSAVE_SLOT: &str = "quicksave";
+/// The slot that is saved to on every `State::goto`.
+pub const AUTOSAVE_SLOT: &str = "autosave";
+
+/// Somewhere that saves can be kept between sessions, in named slots.
+pub trait SaveStorage: std::fmt::Debug {
+    /// The names of every slot which has a save in it, sorted.
+    fn list(&self) -> Result<Vec<String>, SaveError>;
+    /// The save in the slot, if there is one.
+    fn read(&self, slot: &str) -> Result<Option<String>, SaveError>;
+    /// Write the save into the slot, replacing any save already there.
+    fn write(&self, slot: &str, data: &str) -> Result<(), SaveError>;
+    /// Remove the save in the slot, if there is one.
+    fn delete(&self, slot: &str) -> Result<(), SaveError>;
+}
+
+/// Names that Windows won't create files with, whatever the case or extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Check that a slot name is something that every storage can handle.
+/// This is deliberately strict, since slot names end up as file names. Windows drops a trailing
+/// space from file names, so it isn't allowed either.
+pub fn check_slot(slot: &str) -> Result<(), SaveError> {
+    let is_valid = !slot.is_empty()
+        && slot.len() <= 64
+        && slot
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == ' ')
+        && !slot.ends_with(' ')
+        && !RESERVED_NAMES
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(slot));
+    if is_valid {
+        Ok(())
+    } else {
+        Err(SaveError::InvalidSlot(slot.to_owned()))
+    }
+}
+
+/// Keeps saves only for as long as it exists. Used when there is nowhere better to put them.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    saves: RefCell<BTreeMap<String, String>>,
+}
+impl SaveStorage for MemoryStorage {
+    fn list(&self) -> Result<Vec<String>, SaveError> {
+        Ok(self.saves.borrow().keys().cloned().collect())
+    }
+
+    fn read(&self, slot: &str) -> Result<Option<String>, SaveError> {
+        check_slot(slot)?;
+        Ok(self.saves.borrow().get(slot).cloned())
+    }
+
+    fn write(&self, slot: &str, data: &str) -> Result<(), SaveError> {
+        check_slot(slot)?;
+        self.saves
+            .borrow_mut()
+            .insert(slot.to_owned(), data.to_owned());
+        Ok(())
+    }
+
+    fn delete(&self, slot: &str) -> Result<(), SaveError> {
+        check_slot(slot)?;
+        self.saves.borrow_mut().remove(slot);
+        Ok(())
+    }
+}
+
+/// Keeps each save as a `<slot>.json` file in a directory.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct FileStorage {
+    directory: std::path::PathBuf,
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStorage {
+    const EXTENSION: &'static str = "json";
+
+    /// The directory is created when the first save is written.
+    pub fn new(directory: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    fn path(&self, slot: &str) -> Result<std::path::PathBuf, SaveError> {
+        check_slot(slot)?;
+        Ok(self.directory.join(slot).with_extension(Self::EXTENSION))
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl SaveStorage for FileStorage {
+    fn list(&self) -> Result<Vec<String>, SaveError> {
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            // Nothing has been saved yet
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(SaveError::Storage(err.to_string())),
+        };
+
+        let mut slots = Vec::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|err| SaveError::Storage(err.to_string()))?
+                .path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(Self::EXTENSION) {
+                continue;
+            }
+            if let Some(slot) = path.file_stem().and_then(|stem| stem.to_str()) {
+                slots.push(slot.to_owned());
+            }
+        }
+        slots.sort();
+        Ok(slots)
+    }
+
+    fn read(&self, slot: &str) -> Result<Option<String>, SaveError> {
+        match std::fs::read_to_string(self.path(slot)?) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(SaveError::Storage(err.to_string())),
+        }
+    }
+
+    fn write(&self, slot: &str, data: &str) -> Result<(), SaveError> {
+        let path = self.path(slot)?;
+        std::fs::create_dir_all(&self.directory)
+            .and_then(|_| std::fs::write(path, data))
+            .map_err(|err| SaveError::Storage(err.to_string()))
+    }
+
+    fn delete(&self, slot: &str) -> Result<(), SaveError> {
+        match std::fs::remove_file(self.path(slot)?) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(SaveError::Storage(err.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slot_names() {
+        for slot in &[
+            "autosave",
+            "Slot 1",
+            "before-the_boss",
+            " leading",
+            "console",
+            "COM10",
+            "été",
+        ] {
+            assert_eq!(check_slot(slot), Ok(()), "{}", slot);
+        }
+        let too_long = "a".repeat(65);
+        for slot in &[
+            "",
+            "../escape",
+            "a/b",
+            "a\\b",
+            "save.json",
+            "a:b",
+            "trailing ",
+            "con",
+            "NUL",
+            "Com1",
+            "lpt9",
+            too_long.as_str(),
+        ] {
+            assert_eq!(
+                check_slot(slot),
+                Err(SaveError::InvalidSlot((*slot).to_owned())),
+                "{}",
+                slot
+            );
+        }
+        assert_eq!(check_slot(&"a".repeat(64)), Ok(()));
+    }
+
+    fn use_storage(storage: &dyn SaveStorage) {
+        assert_eq!(storage.list(), Ok(Vec::new()));
+        assert_eq!(storage.read("b"), Ok(None));
+        storage.write("b", "second").unwrap();
+        storage.write("a", "first").unwrap();
+        storage.write("b", "replaced").unwrap();
+        assert_eq!(storage.list(), Ok(vec!["a".to_owned(), "b".to_owned()]));
+        assert_eq!(storage.read("b"), Ok(Some("replaced".to_owned())));
+        assert_eq!(
+            storage.write("../a", "data"),
+            Err(SaveError::InvalidSlot("../a".to_owned()))
+        );
+        assert_eq!(
+            storage.read("../a"),
+            Err(SaveError::InvalidSlot("../a".to_owned()))
+        );
+        assert_eq!(
+            storage.delete("con"),
+            Err(SaveError::InvalidSlot("con".to_owned()))
+        );
+        storage.delete("a").unwrap();
+        storage.delete("a").unwrap();
+        assert_eq!(storage.list(), Ok(vec!["b".to_owned()]));
+    }
+
+    #[test]
+    fn memory_storage() {
+        use_storage(&MemoryStorage::default());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn file_storage() {
+        let directory =
+            std::env::temp_dir().join(format!("aleph-storage-test-{}", std::process::id()));
+        use_storage(&FileStorage::new(&directory));
+        assert!(directory.join("b.json").exists());
+        assert_eq!(
+            FileStorage::new(&directory).read("../b"),
+            Err(SaveError::InvalidSlot("../b".to_owned()))
+        );
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,566 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    path::Path,
+    rc::Rc,
+};
+
+use super::{
+    ChoiceCallback, Frontend, InputCallback, InputKind, InputValidator, Prompt, Support, Timeout,
+    Validation, ERROR_TITLE,
+};
+use crate::{
+    diagnostics::Diagnostic,
+    engine::{
+        bars::RenderedBar,
+        error::EngineError,
+        replay::{Replay, ReplayStep},
+        save::SaveValue,
+        storage::{MemoryStorage, SaveStorage},
+        Handle,
+    },
+    markup, RunOptions,
+};
+
+/// A frontend that just records what it was told to display, for driving games from Rust.
+#[derive(Debug, Default)]
+pub struct HeadlessFrontend {
+    title: RefCell<String>,
+    text: RefCell<String>,
+    prompt: RefCell<Option<Prompt>>,
+    bars: RefCell<Vec<RenderedBar>>,
+    /// The error which stopped the game, if any.
+    error: RefCell<Option<EngineError>>,
+    /// How many seconds have passed, as far as the game knows. This only moves forward when
+    /// `Headless::advance` is called, so that timed choices can be tested without waiting.
+    clock: Cell<f64>,
+    /// When the timed choice being asked runs out of time, on the clock
+    deadline: Cell<f64>,
+}
+impl Frontend for HeadlessFrontend {
+    fn support(&self) -> Support {
+        // Images are only ever shown as their alt text
+        Support::default()
+    }
+
+    fn set_title(&self, text: &str) {
+        *self.title.borrow_mut() = text.to_owned();
+    }
+    fn append_title(&self, text: &str) {
+        self.title.borrow_mut().push_str(text);
+    }
+    fn clear_title(&self) {
+        self.title.borrow_mut().clear();
+    }
+
+    fn set_text(&self, text: &str) {
+        *self.text.borrow_mut() = text.to_owned();
+    }
+    fn append_text(&self, text: &str) {
+        self.text.borrow_mut().push_str(text);
+    }
+    fn clear_text(&self) {
+        self.text.borrow_mut().clear();
+    }
+
+    fn ask_choice(
+        &self,
+        choices: Vec<String>,
+        callback: ChoiceCallback,
+    ) -> Result<(), EngineError> {
+        *self.prompt.borrow_mut() = Some(Prompt::Choice {
+            choices,
+            callback,
+            timeout: None,
+        });
+        Ok(())
+    }
+
+    fn ask_choice_timed(
+        &self,
+        choices: Vec<String>,
+        timeout: Timeout,
+        callback: ChoiceCallback,
+    ) -> Result<(), EngineError> {
+        self.deadline.set(self.clock.get() + timeout.seconds);
+        *self.prompt.borrow_mut() = Some(Prompt::Choice {
+            choices,
+            callback,
+            timeout: Some(timeout),
+        });
+        Ok(())
+    }
+
+    fn ask_input(
+        &self,
+        default_text: &str,
+        kind: InputKind,
+        validator: InputValidator,
+        live: bool,
+        callback: InputCallback,
+    ) -> Result<(), EngineError> {
+        *self.prompt.borrow_mut() = Some(Prompt::Input {
+            default_text: default_text.to_owned(),
+            kind,
+            validator,
+            live,
+            callback,
+        });
+        Ok(())
+    }
+
+    fn set_bars(&self, bars: &[RenderedBar]) {
+        *self.bars.borrow_mut() = bars.to_vec();
+    }
+
+    fn show_error(&self, error: &EngineError) {
+        *self.title.borrow_mut() = ERROR_TITLE.to_owned();
+        *self.text.borrow_mut() = markup::escape(&error.to_string());
+        *self.prompt.borrow_mut() = None;
+        *self.error.borrow_mut() = Some(error.clone());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeadlessError {
+    /// The game failed to load or its `entry` function failed.
+    Start(EngineError),
+    /// The game is not waiting on anything.
+    NoPrompt,
+    /// Tried to pick a choice, but the game is waiting on text input.
+    ExpectedInput,
+    /// Tried to submit text, but the game is waiting on a choice.
+    ExpectedChoice,
+    /// There is no choice with that index or text.
+    UnknownChoice(String),
+    /// The submitted text was rejected by the script's validator, with its message for why.
+    InvalidInput { text: String, message: String },
+    /// The game stopped because of an error while answering.
+    Engine(EngineError),
+    /// The new scripts couldn't be swapped in, so the game carried on as it was.
+    Reload(EngineError),
+    /// A replay played out differently than when it was recorded, at the step (starting at 1).
+    Diverged { step: usize, reason: String },
+    /// Tried to run out the time on a choice, but the game isn't asking a timed choice.
+    Untimed,
+}
+impl std::fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Start(err) => write!(f, "Failed to start: {}", err),
+            Self::NoPrompt => write!(f, "The game is not waiting on any input"),
+            Self::ExpectedInput => write!(f, "The game is waiting on text input, not a choice"),
+            Self::ExpectedChoice => write!(f, "The game is waiting on a choice, not text input"),
+            Self::UnknownChoice(choice) => write!(f, "There is no choice '{}'", choice),
+            Self::InvalidInput { text, message } => {
+                write!(f, "The input '{}' was rejected: {}", text, message)
+            }
+            Self::Engine(err) => write!(f, "{}", err),
+            Self::Reload(err) => write!(f, "Failed to reload: {}", err),
+            Self::Diverged { step, reason } => {
+                write!(f, "Replay diverged at step {}: {}", step, reason)
+            }
+            Self::Untimed => write!(f, "The game is not asking a timed choice"),
+        }
+    }
+}
+
+/// Runs a game without displaying it anywhere, so that it can be played from Rust.
+/// ```rust
+/// use aleph_naught::frontend::headless::Headless;
+///
+/// let game = Headless::from_files(&["examples/demon.rune"]).unwrap();
+/// assert_eq!(game.title(), "Town of Plenty");
+/// game.choose_text("Enter Tavern").unwrap();
+/// assert_eq!(game.title(), "Cheap Tavern");
+/// ```
+#[derive(Debug)]
+pub struct Headless {
+    frontend: Rc<HeadlessFrontend>,
+    game: Handle,
+    /// Warnings from compiling the scripts
+    diagnostics: Vec<Diagnostic>,
+}
+impl Headless {
+    /// Run the `entry` function of the sources, keeping any saves in `storage`.
+    pub fn new(
+        sources: rune::Sources,
+        storage: Rc<dyn SaveStorage>,
+    ) -> Result<Self, HeadlessError> {
+        Self::with_options(sources, storage, &RunOptions::default())
+    }
+
+    pub fn with_options(
+        mut sources: rune::Sources,
+        storage: Rc<dyn SaveStorage>,
+        options: &RunOptions,
+    ) -> Result<Self, HeadlessError> {
+        let frontend = Rc::new(HeadlessFrontend::default());
+        let (game, diagnostics) =
+            crate::run_entry(&mut sources, frontend.clone(), storage, options)
+                .map_err(HeadlessError::Start)?;
+        Ok(Self {
+            frontend,
+            game,
+            diagnostics,
+        })
+    }
+
+    /// Load the files and run their `entry` function, with any string tables next to them.
+    /// Saves are only kept in memory.
+    pub fn from_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self, HeadlessError> {
+        let sources = crate::load_files(paths).map_err(HeadlessError::Start)?;
+        let options = RunOptions {
+            locales: crate::load_locales(&crate::find_locales(paths))
+                .map_err(HeadlessError::Start)?,
+            ..RunOptions::default()
+        };
+        Self::with_options(sources, Rc::new(MemoryStorage::default()), &options)
+    }
+
+    /// Run the `entry` function of code given as pairs of `(name, code)`.
+    /// Saves are only kept in memory.
+    pub fn from_inline<N: AsRef<str>, C: AsRef<str>>(
+        inline_sources: &[(N, C)],
+    ) -> Result<Self, HeadlessError> {
+        Self::from_sources(crate::load_inline(inline_sources))
+    }
+
+    /// Run the `entry` function of the sources.
+    /// Saves are only kept in memory.
+    pub fn from_sources(sources: rune::Sources) -> Result<Self, HeadlessError> {
+        Self::new(sources, Rc::new(MemoryStorage::default()))
+    }
+
+    /// Warnings from compiling the scripts, which didn't stop the game from starting.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// The title as plain text, with any markup removed.
+    pub fn title(&self) -> String {
+        markup::to_plain(&markup::parse(&self.frontend.title.borrow()))
+    }
+
+    /// The text as plain text, with any markup removed.
+    pub fn text(&self) -> String {
+        markup::to_plain(&markup::parse(&self.frontend.text.borrow()))
+    }
+
+    /// The title as it was given, markup and all.
+    pub fn title_markup(&self) -> String {
+        self.frontend.title.borrow().clone()
+    }
+
+    /// The text as it was given, markup and all.
+    pub fn text_markup(&self) -> String {
+        self.frontend.text.borrow().clone()
+    }
+
+    /// The scene the game is in, if it has entered one.
+    pub fn scene(&self) -> Option<String> {
+        self.game.current_scene()
+    }
+
+    /// A copy of the info, in the same form as it is saved.
+    pub fn info(&self) -> Result<BTreeMap<String, SaveValue>, HeadlessError> {
+        self.game.info().map_err(HeadlessError::Engine)
+    }
+
+    /// The bars, as they were last refreshed.
+    pub fn bars(&self) -> Vec<RenderedBar> {
+        self.frontend.bars.borrow().clone()
+    }
+
+    /// The text of each choice currently being asked, if a choice is being asked.
+    pub fn choices(&self) -> Option<Vec<String>> {
+        match &*self.frontend.prompt.borrow() {
+            Some(Prompt::Choice { choices, .. }) => Some(choices.clone()),
+            _ => None,
+        }
+    }
+
+    /// The default text of the input currently being asked for, if input is being asked for.
+    pub fn input(&self) -> Option<String> {
+        match &*self.frontend.prompt.borrow() {
+            Some(Prompt::Input { default_text, .. }) => Some(default_text.clone()),
+            _ => None,
+        }
+    }
+
+    /// The kind of the input currently being asked for, if input is being asked for.
+    pub fn input_kind(&self) -> Option<InputKind> {
+        match &*self.frontend.prompt.borrow() {
+            Some(Prompt::Input { kind, .. }) => Some(kind.clone()),
+            _ => None,
+        }
+    }
+
+    /// The error which stopped the game, if it was stopped by one.
+    pub fn error(&self) -> Option<EngineError> {
+        self.frontend.error.borrow().clone()
+    }
+
+    /// Whether the game is still waiting on the player for anything.
+    pub fn is_waiting(&self) -> bool {
+        self.frontend.prompt.borrow().is_some()
+    }
+
+    /// Anything the script has printed since the last time this was called.
+    pub fn output(&self) -> Option<String> {
+        self.game.drain_output().filter(|output| !output.is_empty())
+    }
+
+    /// Pick the choice at `index`.
+    pub fn choose(&self, index: usize) -> Result<(), HeadlessError> {
+        // Take the prompt out before calling anything, as the callback will likely set a new one.
+        let prompt = self.frontend.prompt.borrow_mut().take();
+        match prompt {
+            Some(Prompt::Choice {
+                choices,
+                callback,
+                timeout,
+            }) => {
+                if index >= choices.len() {
+                    *self.frontend.prompt.borrow_mut() = Some(Prompt::Choice {
+                        choices,
+                        callback,
+                        timeout,
+                    });
+                    return Err(HeadlessError::UnknownChoice(index.to_string()));
+                }
+                callback(index).map_err(HeadlessError::Engine)
+            }
+            Some(prompt) => {
+                *self.frontend.prompt.borrow_mut() = Some(prompt);
+                Err(HeadlessError::ExpectedInput)
+            }
+            None => Err(HeadlessError::NoPrompt),
+        }
+    }
+
+    /// Pick the first choice whose text is `text`.
+    pub fn choose_text(&self, text: &str) -> Result<(), HeadlessError> {
+        let choices = match self.choices() {
+            Some(choices) => choices,
+            None if self.is_waiting() => return Err(HeadlessError::ExpectedInput),
+            None => return Err(HeadlessError::NoPrompt),
+        };
+        let index = choices
+            .iter()
+            .position(|choice| choice == text)
+            .ok_or_else(|| HeadlessError::UnknownChoice(text.to_owned()))?;
+        self.choose(index)
+    }
+
+    /// How many seconds are left to pick the choice being asked, if it is timed.
+    pub fn time_left(&self) -> Option<f64> {
+        match &*self.frontend.prompt.borrow() {
+            Some(Prompt::Choice {
+                timeout: Some(_), ..
+            }) => Some((self.frontend.deadline.get() - self.frontend.clock.get()).max(0.0)),
+            _ => None,
+        }
+    }
+
+    /// Move the game's clock forward. Time only passes in a headless game when this is called,
+    /// such as by the terminal frontends with however long the player took.
+    /// If the choice being asked runs out of time, then it times out as if the player never
+    /// picked. Returns whether it did.
+    pub fn advance(&self, seconds: f64) -> Result<bool, HeadlessError> {
+        self.frontend
+            .clock
+            .set(self.frontend.clock.get() + seconds.max(0.0));
+        match self.time_left() {
+            Some(left) if left <= 0.0 => self.expire().map(|_| true),
+            _ => Ok(false),
+        }
+    }
+
+    /// Run out the time on the timed choice being asked, straight away.
+    pub fn expire(&self) -> Result<(), HeadlessError> {
+        let prompt = self.frontend.prompt.borrow_mut().take();
+        match prompt {
+            Some(Prompt::Choice {
+                timeout: Some(timeout),
+                ..
+            }) => (timeout.callback)().map_err(HeadlessError::Engine),
+            Some(prompt) => {
+                *self.frontend.prompt.borrow_mut() = Some(prompt);
+                Err(HeadlessError::Untimed)
+            }
+            None => Err(HeadlessError::NoPrompt),
+        }
+    }
+
+    /// Undo the last choice, going back to the scene it was made in with the info from before it.
+    /// Returns whether there was anything to undo.
+    pub fn undo(&self) -> Result<bool, HeadlessError> {
+        // Take the prompt out, as it belongs to the scene being left. The scene gone back to will
+        // ask again.
+        let prompt = self.frontend.prompt.borrow_mut().take();
+        let error = self.frontend.error.borrow_mut().take();
+        match self.game.undo() {
+            Ok(true) => Ok(true),
+            Ok(false) => {
+                *self.frontend.prompt.borrow_mut() = prompt;
+                *self.frontend.error.borrow_mut() = error;
+                Ok(false)
+            }
+            Err(err) => Err(HeadlessError::Engine(err)),
+        }
+    }
+
+    /// Show the game in another language, entering the current scene again in it.
+    pub fn set_language(&self, language: &str) -> Result<(), HeadlessError> {
+        // The prompt belongs to the scene as it was shown, which asks again once entered
+        let prompt = self.frontend.prompt.borrow_mut().take();
+        self.game.set_language(language).map_err(|err| {
+            if self.frontend.error.borrow().is_none() {
+                *self.frontend.prompt.borrow_mut() = prompt;
+            }
+            HeadlessError::Engine(err)
+        })
+    }
+
+    /// Swap in new scripts, keeping the info and the scene the game is in, which is entered again.
+    /// See `crate::reload`. On success, the warnings from compiling replace `diagnostics`.
+    pub fn reload(
+        &mut self,
+        mut sources: rune::Sources,
+        options: &RunOptions,
+    ) -> Result<(), HeadlessError> {
+        // The game may be carrying on from an error screen, if the scripts were fixed
+        let error = self.frontend.error.borrow_mut().take();
+        match crate::reload(&mut self.game, &mut sources, options) {
+            Ok(diagnostics) => {
+                self.diagnostics = diagnostics;
+                Ok(())
+            }
+            // The error was shown, so the game was stopped by it
+            Err(err) if self.frontend.error.borrow().is_some() => Err(HeadlessError::Engine(err)),
+            Err(err) => {
+                *self.frontend.error.borrow_mut() = error;
+                Err(HeadlessError::Reload(err))
+            }
+        }
+    }
+
+    /// Everything the player has done so far, to be saved and played back later with
+    /// `play_replay`.
+    pub fn replay(&self) -> Result<Replay, HeadlessError> {
+        self.game.replay().map_err(HeadlessError::Engine)
+    }
+
+    /// Do each step of the replay, stopping at the first one that can't be done the same way as
+    /// when it was recorded. This should be called on a game that was just started, so that it
+    /// is in the same place as the recording started from.
+    /// Errors from the scripts are returned as they are, as reproducing them is usually the point.
+    /// If the recording started in another language, then it is switched to first.
+    pub fn play_replay(&self, replay: &Replay) -> Result<(), HeadlessError> {
+        if let Some(language) = &replay.language {
+            if self.game.language().as_ref() != Some(language) {
+                self.set_language(language)?;
+            }
+        }
+        for (number, step) in replay.steps.iter().enumerate() {
+            let diverged = |err: HeadlessError| match err {
+                HeadlessError::Engine(err) => HeadlessError::Engine(err),
+                err => HeadlessError::Diverged {
+                    step: number + 1,
+                    reason: err.to_string(),
+                },
+            };
+            match step {
+                ReplayStep::Choice { index, text } => {
+                    let choice = self
+                        .choices()
+                        .and_then(|choices| choices.get(*index).cloned());
+                    if let Some(choice) = choice.filter(|choice| choice != text) {
+                        return Err(HeadlessError::Diverged {
+                            step: number + 1,
+                            reason: format!(
+                                "Choice {} was '{}', but is now '{}'",
+                                index + 1,
+                                text,
+                                choice
+                            ),
+                        });
+                    }
+                    self.choose(*index).map_err(diverged)?;
+                }
+                ReplayStep::Input { text } => self.submit(text).map_err(diverged)?,
+                ReplayStep::Timeout => self.expire().map_err(diverged)?,
+                ReplayStep::Language(language) => self.set_language(language).map_err(diverged)?,
+                ReplayStep::Undo => {
+                    if !self.undo().map_err(diverged)? {
+                        return Err(HeadlessError::Diverged {
+                            step: number + 1,
+                            reason: "There was nothing to undo".to_owned(),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the text against the validator of the input being asked for, without submitting it.
+    /// This is `None` unless the script asked for the input to be checked as the player types.
+    pub fn check(&self, text: &str) -> Result<Option<Validation>, HeadlessError> {
+        // Taken out while the validator runs, as it is a script and may use the frontend
+        let prompt = self.frontend.prompt.borrow_mut().take();
+        let result = match &prompt {
+            Some(Prompt::Input {
+                validator,
+                live: true,
+                ..
+            }) => validator(text.to_owned())
+                .map(Some)
+                .map_err(HeadlessError::Engine),
+            Some(Prompt::Input { .. }) => Ok(None),
+            Some(_) => Err(HeadlessError::ExpectedChoice),
+            None => Err(HeadlessError::NoPrompt),
+        };
+        // Unless the validator failed, and the error screen replaced the prompt
+        if result.is_ok() {
+            *self.frontend.prompt.borrow_mut() = prompt;
+        }
+        result
+    }
+
+    /// Submit text to the input currently being asked for.
+    /// If the script rejects it, then the input is still being asked for.
+    pub fn submit(&self, text: &str) -> Result<(), HeadlessError> {
+        let prompt = self.frontend.prompt.borrow_mut().take();
+        match prompt {
+            Some(Prompt::Input {
+                default_text,
+                kind,
+                validator,
+                live,
+                callback,
+            }) => {
+                if let Err(message) = validator(text.to_owned()).map_err(HeadlessError::Engine)? {
+                    *self.frontend.prompt.borrow_mut() = Some(Prompt::Input {
+                        default_text,
+                        kind,
+                        validator,
+                        live,
+                        callback,
+                    });
+                    return Err(HeadlessError::InvalidInput {
+                        text: text.to_owned(),
+                        message,
+                    });
+                }
+                callback(text.to_owned()).map_err(HeadlessError::Engine)
+            }
+            Some(prompt) => {
+                *self.frontend.prompt.borrow_mut() = Some(prompt);
+                Err(HeadlessError::ExpectedChoice)
+            }
+            None => Err(HeadlessError::NoPrompt),
+        }
+    }
+}
@@ -0,0 +1,252 @@
+pub mod headless;
+pub mod terminal;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tui;
+pub mod watch;
+
+use crate::engine::{bars::RenderedBar, error::EngineError};
+
+/// The title shown on the error screen by `Frontend::show_error`.
+pub const ERROR_TITLE: &str = "Error";
+/// The message shown when a validator rejects input without saying why.
+pub const INVALID_INPUT_MESSAGE: &str = "Invalid input";
+
+/// Called with the index of the choice that was picked.
+/// By the time an error is returned, it has already been shown with `Frontend::show_error`.
+pub type ChoiceCallback = Box<dyn FnOnce(usize) -> Result<(), EngineError>>;
+/// Whether entered text was accepted, or the message to show the player for why it wasn't.
+pub type Validation = Result<(), String>;
+/// Checks whether the entered text is acceptable.
+pub type InputValidator = Box<dyn Fn(String) -> Result<Validation, EngineError>>;
+/// Called with the entered text once it has passed validation.
+pub type InputCallback = Box<dyn FnOnce(String) -> Result<(), EngineError>>;
+/// Called when the player didn't answer in time.
+pub type TimeoutCallback = Box<dyn FnOnce() -> Result<(), EngineError>>;
+
+/// How long the player has to pick a choice, and what happens if they don't.
+pub struct Timeout {
+    /// How long the player has, from when the choice is asked
+    pub seconds: f64,
+    /// Called instead of the choice's callback once the time runs out
+    pub callback: TimeoutCallback,
+}
+impl std::fmt::Debug for Timeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Timeout")
+            .field("seconds", &self.seconds)
+            .finish()
+    }
+}
+
+/// What sort of input is being asked for, which decides how the player enters it.
+/// Whatever the kind, the answer is given to the validator and callback as text, such as `"12"`
+/// for a number or the option's text for a select.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputKind {
+    /// A single line of text.
+    Line,
+    /// Text which may span several lines.
+    Multiline,
+    /// A single line of text which is hidden as it is typed, like a password.
+    Secret,
+    /// A number from `min` to `max`, inclusive. If `integer`, then only whole numbers.
+    Number { min: f64, max: f64, integer: bool },
+    /// One of the options.
+    Select(Vec<String>),
+}
+impl InputKind {
+    /// Check that the text is something of this kind, such as a number in the range.
+    pub fn check(&self, text: &str) -> Validation {
+        match self {
+            Self::Line | Self::Multiline | Self::Secret => Ok(()),
+            Self::Number { min, max, integer } => {
+                let number = if *integer {
+                    text.trim().parse::<i64>().ok().map(|number| number as f64)
+                } else {
+                    text.trim()
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|number| number.is_finite())
+                };
+                match number {
+                    Some(number) if number >= *min && number <= *max => Ok(()),
+                    Some(_) => Err(format!("Enter a number from {} to {}", min, max)),
+                    None if *integer => Err("Enter a whole number".to_owned()),
+                    None => Err("Enter a number".to_owned()),
+                }
+            }
+            Self::Select(options) => {
+                if options.iter().any(|option| option == text) {
+                    Ok(())
+                } else {
+                    Err("Choose one of the options".to_owned())
+                }
+            }
+        }
+    }
+}
+
+/// What a frontend is able to display, so that scripts can fall back to something else.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Support {
+    /// Images are displayed, rather than just their alt text.
+    pub image: bool,
+}
+
+/// The backend which actually displays the game and gets input from the player.
+/// `State` only ever talks to the player through this, so that the engine does not care whether
+/// it is running in a browser, a terminal, or a test.
+pub trait Frontend: std::fmt::Debug {
+    fn support(&self) -> Support;
+
+    fn set_title(&self, text: &str);
+    fn append_title(&self, text: &str);
+    fn clear_title(&self);
+
+    fn set_text(&self, text: &str);
+    fn append_text(&self, text: &str);
+    fn clear_text(&self);
+
+    /// Present the choices to the player, calling `callback` with the index of the picked one.
+    /// The choices must be removed *before* the callback is called, as the callback is likely to
+    /// present new choices.
+    /// Failing to display them is returned as an `EngineError::Frontend`.
+    fn ask_choice(&self, choices: Vec<String>, callback: ChoiceCallback)
+        -> Result<(), EngineError>;
+
+    /// Like `ask_choice`, but if the player hasn't picked a choice after `timeout.seconds`, then
+    /// the choices are removed and the timeout's callback is called instead. The time that is
+    /// left should be shown to the player.
+    fn ask_choice_timed(
+        &self,
+        choices: Vec<String>,
+        timeout: Timeout,
+        callback: ChoiceCallback,
+    ) -> Result<(), EngineError>;
+
+    /// Ask the player for input of the kind, starting out with `default_text`.
+    /// `callback` is only called once the text passes `validator`, and like `ask_choice` the
+    /// input must be removed before it is called. When the validator rejects the text, its
+    /// message should be shown by the input. If `live`, then the text should also be validated as
+    /// the player types, where that is possible.
+    fn ask_input(
+        &self,
+        default_text: &str,
+        kind: InputKind,
+        validator: InputValidator,
+        live: bool,
+        callback: InputCallback,
+    ) -> Result<(), EngineError>;
+
+    /// Display the bars, replacing any that were displayed before.
+    /// Backends without anywhere to put them may ignore them.
+    fn set_bars(&self, bars: &[RenderedBar]);
+
+    /// Replace everything with an error screen, as the game can't continue.
+    /// Any choices or input being asked for should be removed.
+    fn show_error(&self, error: &EngineError);
+}
+
+/// Something that the game is waiting on the player to answer.
+/// Backends which are driven from Rust, rather than by events, hold onto this until they have an
+/// answer for it.
+pub enum Prompt {
+    Choice {
+        choices: Vec<String>,
+        callback: ChoiceCallback,
+        /// The time limit, for a timed choice
+        timeout: Option<Timeout>,
+    },
+    Input {
+        default_text: String,
+        kind: InputKind,
+        validator: InputValidator,
+        /// Whether the text should be checked as the player types
+        live: bool,
+        callback: InputCallback,
+    },
+}
+impl std::fmt::Debug for Prompt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Choice {
+                choices, timeout, ..
+            } => f
+                .debug_struct("Choice")
+                .field("choices", choices)
+                .field("timeout", timeout)
+                .finish(),
+            Self::Input {
+                default_text,
+                kind,
+                live,
+                ..
+            } => f
+                .debug_struct("Input")
+                .field("default_text", default_text)
+                .field("kind", kind)
+                .field("live", live)
+                .finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_text() {
+        for kind in &[InputKind::Line, InputKind::Multiline, InputKind::Secret] {
+            assert_eq!(kind.check(""), Ok(()));
+            assert_eq!(kind.check("anything\nat all"), Ok(()));
+        }
+    }
+
+    #[test]
+    fn check_number() {
+        let whole = InputKind::Number {
+            min: 1.0,
+            max: 10.0,
+            integer: true,
+        };
+        assert_eq!(whole.check("1"), Ok(()));
+        assert_eq!(whole.check(" 10 "), Ok(()));
+        assert_eq!(
+            whole.check("11"),
+            Err("Enter a number from 1 to 10".to_owned())
+        );
+        assert_eq!(
+            whole.check("0"),
+            Err("Enter a number from 1 to 10".to_owned())
+        );
+        assert_eq!(whole.check("2.5"), Err("Enter a whole number".to_owned()));
+        assert_eq!(whole.check(""), Err("Enter a whole number".to_owned()));
+
+        let any = InputKind::Number {
+            min: -0.5,
+            max: 0.5,
+            integer: false,
+        };
+        assert_eq!(any.check("0.25"), Ok(()));
+        assert_eq!(any.check("-0.5"), Ok(()));
+        assert_eq!(
+            any.check("1e3"),
+            Err("Enter a number from -0.5 to 0.5".to_owned())
+        );
+        assert_eq!(any.check("NaN"), Err("Enter a number".to_owned()));
+        assert_eq!(any.check("inf"), Err("Enter a number".to_owned()));
+        assert_eq!(any.check("half"), Err("Enter a number".to_owned()));
+    }
+
+    #[test]
+    fn check_select() {
+        let kind = InputKind::Select(vec!["Red".to_owned(), "Blue".to_owned()]);
+        assert_eq!(kind.check("Blue"), Ok(()));
+        assert_eq!(
+            kind.check("blue"),
+            Err("Choose one of the options".to_owned())
+        );
+        assert_eq!(kind.check(""), Err("Choose one of the options".to_owned()));
+    }
+}
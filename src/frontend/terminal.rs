@@ -0,0 +1,294 @@
+use std::{
+    io::{self, BufRead, Write},
+    time::Instant,
+};
+
+use super::{
+    headless::{Headless, HeadlessError},
+    watch::{Watch, WATCH_INTERVAL},
+    InputKind,
+};
+use crate::{
+    engine::bars::{BarContent, RenderedBar},
+    markup,
+};
+
+/// What the player enters to undo their last choice.
+pub const UNDO_COMMAND: &str = ":undo";
+
+/// Plays a game line-by-line over a simple reader and writer, such as stdin and stdout.
+/// The game itself is run headless, and this just prints out whatever it changed after each
+/// answer the player gives.
+/// Entering `UNDO_COMMAND` at any prompt undoes the last choice.
+/// For timed choices the time left is printed with the choices. Reading a line blocks, so the
+/// choice can't time out while waiting on it, and an answer that comes in after the time ran out
+/// is ignored, telling the player it was too late.
+/// Multi-line input is read until an empty line. Secret input is echoed like any other, as this
+/// only has a reader to work with, so the player is warned before typing it.
+#[derive(Debug)]
+pub struct Terminal<R, W> {
+    game: Headless,
+    input: R,
+    output: W,
+    /// The title markup that was last printed, so that we don't print it again when it is
+    /// unchanged.
+    shown_title: String,
+    /// The text markup that was last printed.
+    shown_text: String,
+    /// The bars that were last printed.
+    shown_bars: Vec<RenderedBar>,
+    /// Reloads the scripts when they change, checked before each prompt.
+    watch: Option<Watch>,
+    /// When the current prompt was shown, to know how long the player took to answer it.
+    asked: Instant,
+}
+impl<R: BufRead, W: Write> Terminal<R, W> {
+    pub fn new(game: Headless, input: R, output: W) -> Self {
+        Self {
+            game,
+            input,
+            output,
+            shown_title: String::new(),
+            shown_text: String::new(),
+            shown_bars: Vec::new(),
+            watch: None,
+            asked: Instant::now(),
+        }
+    }
+
+    /// Reload the scripts whenever their files change. As reading a line blocks, changes are
+    /// picked up before the next prompt, once the player has answered the current one.
+    pub fn with_watch(mut self, watch: Watch) -> Self {
+        self.watch = Some(watch);
+        self
+    }
+
+    pub fn game(&self) -> &Headless {
+        &self.game
+    }
+
+    /// Play until the game stops asking for anything, or the input runs out.
+    pub fn run(&mut self) -> io::Result<()> {
+        // Whether the game has stopped, and we are waiting for the scripts to be changed
+        let mut waiting = false;
+        loop {
+            if let Some(message) = self.watch.as_mut().and_then(|w| w.poll(&mut self.game)) {
+                writeln!(self.output, "[{}]", message)?;
+                waiting = false;
+            }
+            self.show()?;
+
+            if let Some(choices) = self.game.choices() {
+                for (index, choice) in choices.iter().enumerate() {
+                    writeln!(self.output, "  {}) {}", index + 1, choice)?;
+                }
+                if let Some(left) = self.game.time_left() {
+                    writeln!(self.output, "({} seconds to choose)", left.ceil())?;
+                }
+                let line = match self.read_line("> ")? {
+                    Some(line) => line,
+                    None => return Ok(()),
+                };
+                if self.out_of_time()? {
+                    continue;
+                }
+                if line == UNDO_COMMAND {
+                    self.undo()?;
+                    continue;
+                }
+                // Allow picking by either number or the text of the choice
+                let result = match line.parse::<usize>() {
+                    Ok(number) if number >= 1 => self.game.choose(number - 1),
+                    _ => self.game.choose_text(&line),
+                };
+                match result {
+                    // The error screen is printed by `show`
+                    Ok(()) | Err(HeadlessError::Engine(_)) => {}
+                    Err(err) => writeln!(self.output, "{}", err)?,
+                }
+            } else if let Some(default_text) = self.game.input() {
+                let kind = self.game.input_kind().unwrap_or(InputKind::Line);
+                match &kind {
+                    InputKind::Select(options) => {
+                        for (index, option) in options.iter().enumerate() {
+                            writeln!(self.output, "  {}) {}", index + 1, option)?;
+                        }
+                    }
+                    InputKind::Multiline => {
+                        writeln!(self.output, "(Enter an empty line to finish)")?;
+                    }
+                    InputKind::Secret => {
+                        writeln!(self.output, "(What you type will be shown)")?;
+                    }
+                    _ => {}
+                }
+                let mut prompt = if default_text.is_empty() {
+                    "> ".to_owned()
+                } else {
+                    format!("[{}] > ", default_text.replace('\n', " "))
+                };
+                if let InputKind::Number { min, max, .. } = &kind {
+                    prompt = format!("({} to {}) {}", min, max, prompt);
+                }
+                let line = match self.read_line(&prompt)? {
+                    Some(line) => line,
+                    None => return Ok(()),
+                };
+                if line == UNDO_COMMAND {
+                    self.undo()?;
+                    continue;
+                }
+                let text = match &kind {
+                    _ if line.is_empty() => default_text,
+                    InputKind::Multiline => match self.read_lines(line)? {
+                        Some(text) => text,
+                        None => return Ok(()),
+                    },
+                    // Allow picking by either number or the text of the option
+                    InputKind::Select(options) => match line.parse::<usize>() {
+                        Ok(number) if (1..=options.len()).contains(&number) => {
+                            options[number - 1].clone()
+                        }
+                        _ => line,
+                    },
+                    _ => line,
+                };
+                match self.game.submit(&text) {
+                    Ok(()) | Err(HeadlessError::Engine(_)) => {}
+                    Err(HeadlessError::InvalidInput { message, .. }) => {
+                        writeln!(self.output, "{}", message)?
+                    }
+                    Err(err) => writeln!(self.output, "{}", err)?,
+                }
+            } else if self.watch.is_some() {
+                // Rather than ending, give a chance to fix whatever stopped the game
+                if !waiting {
+                    writeln!(self.output, "[Waiting for the scripts to change]")?;
+                    self.output.flush()?;
+                    waiting = true;
+                }
+                std::thread::sleep(WATCH_INTERVAL);
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Let the time the player took to answer pass in the game. Returns whether it timed out the
+    /// choice that was being asked, in which case their answer came too late.
+    fn out_of_time(&mut self) -> io::Result<bool> {
+        let elapsed = self.asked.elapsed().as_secs_f64();
+        match self.game.advance(elapsed) {
+            Ok(true) => {
+                writeln!(self.output, "Out of time! That answer came too late")?;
+                Ok(true)
+            }
+            // The error screen is printed by `show`
+            Err(HeadlessError::Engine(_)) => Ok(true),
+            Ok(false) => Ok(false),
+            Err(err) => {
+                writeln!(self.output, "{}", err)?;
+                Ok(true)
+            }
+        }
+    }
+
+    fn undo(&mut self) -> io::Result<()> {
+        match self.game.undo() {
+            // The error screen is printed by `show`
+            Ok(true) | Err(HeadlessError::Engine(_)) => Ok(()),
+            Ok(false) => writeln!(self.output, "Nothing to undo"),
+            Err(err) => writeln!(self.output, "{}", err),
+        }
+    }
+
+    /// Print out anything that changed since the last time this was called.
+    fn show(&mut self) -> io::Result<()> {
+        if let Some(output) = self.game.output() {
+            write!(self.output, "{}", output)?;
+        }
+
+        let title = self.game.title_markup();
+        let text = self.game.text_markup();
+        if title != self.shown_title && !title.is_empty() {
+            writeln!(self.output)?;
+            writeln!(self.output, "== {} ==", to_ansi(&title))?;
+        }
+
+        if text != self.shown_text {
+            // Only print the new part if the text was just appended to.
+            if title == self.shown_title && text.starts_with(&self.shown_text) {
+                writeln!(self.output, "{}", to_ansi(&text[self.shown_text.len()..]))?;
+            } else if !text.is_empty() {
+                writeln!(self.output, "{}", to_ansi(&text))?;
+            }
+        }
+
+        // Bars are only printed when they change, as they would otherwise repeat after every choice
+        let bars = self.game.bars();
+        if bars != self.shown_bars {
+            for bar in bars.iter() {
+                match &bar.content {
+                    BarContent::Text(text) => {
+                        writeln!(self.output, "[{}] {}", bar.name, to_ansi(text))?
+                    }
+                    BarContent::Rows(rows) => {
+                        writeln!(self.output, "[{}]", bar.name)?;
+                        for (label, value) in rows {
+                            if value.is_empty() {
+                                writeln!(self.output, "    {}", to_ansi(label))?;
+                            } else {
+                                writeln!(
+                                    self.output,
+                                    "    {}: {}",
+                                    to_ansi(label),
+                                    to_ansi(value)
+                                )?;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        self.shown_title = title;
+        self.shown_text = text;
+        self.shown_bars = bars;
+        Ok(())
+    }
+
+    /// Read the rest of a multi-line input which started with `first`, until an empty line.
+    /// `None` if there is no more input.
+    fn read_lines(&mut self, first: String) -> io::Result<Option<String>> {
+        let mut text = first;
+        loop {
+            match self.read_line("")? {
+                Some(line) if line.is_empty() => return Ok(Some(text)),
+                Some(line) => {
+                    text.push('\n');
+                    text.push_str(&line);
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Read a line, without the trailing newline. `None` if there is no more input.
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        write!(self.output, "{}", prompt)?;
+        self.output.flush()?;
+
+        self.asked = Instant::now();
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let len = line.trim_end_matches(&['\r', '\n'][..]).len();
+        line.truncate(len);
+        Ok(Some(line))
+    }
+}
+
+fn to_ansi(text: &str) -> String {
+    markup::to_ansi(&markup::parse(text))
+}
@@ -0,0 +1,563 @@
+use std::{
+    io::Write,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use super::{
+    headless::{Headless, HeadlessError},
+    watch::{Watch, WATCH_INTERVAL},
+    InputKind,
+};
+use crate::{
+    engine::bars::{BarContent, RenderedBar},
+    markup,
+};
+
+const HELP_CHOICE: &str =
+    "Up/Down: select  1-9/Enter: choose  PgUp/PgDn: scroll  Ctrl+Z: undo  Esc: quit";
+const HELP_INPUT: &str = "Enter: submit  PgUp/PgDn: scroll  Ctrl+Z: undo  Esc: quit";
+const HELP_MULTILINE: &str =
+    "Alt+Enter: new line  Enter: submit  PgUp/PgDn: scroll  Ctrl+Z: undo  Esc: quit";
+const HELP_SELECT: &str =
+    "Up/Down: select  Enter: submit  PgUp/PgDn: scroll  Ctrl+Z: undo  Esc: quit";
+const HELP_ENDED: &str = "The game has ended. Press any key to quit.";
+
+/// How often the countdown of a timed choice is redrawn.
+const TIMER_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The terminal must be at least this wide for bars to be shown.
+const MIN_WIDTH_FOR_SIDEBAR: usize = 60;
+const MAX_SIDEBAR_WIDTH: usize = 30;
+
+/// Plays a game full-screen in the terminal.
+/// Like `Terminal`, the game itself is run headless and this displays whatever it has recorded,
+/// but it redraws everything after each key rather than printing line-by-line.
+#[derive(Debug)]
+pub struct Tui<W> {
+    game: Headless,
+    output: W,
+    /// How many lines the text pane is scrolled down by.
+    scroll: usize,
+    /// The choice which is currently highlighted.
+    selected: usize,
+    /// The line being edited, when the game is asking for input.
+    line: String,
+    /// Position of the cursor in `line`, in chars.
+    cursor: usize,
+    /// A message to show in place of the help line, such as why input was rejected.
+    status: Option<String>,
+    /// Reloads the scripts when they change.
+    watch: Option<Watch>,
+    /// When time was last passed on to the game, for timed choices.
+    last_tick: Instant,
+}
+impl<W: Write> Tui<W> {
+    pub fn new(game: Headless, output: W) -> Self {
+        let mut tui = Self {
+            game,
+            output,
+            scroll: 0,
+            selected: 0,
+            line: String::new(),
+            cursor: 0,
+            status: None,
+            watch: None,
+            last_tick: Instant::now(),
+        };
+        tui.reset_prompt();
+        tui
+    }
+
+    pub fn game(&self) -> &Headless {
+        &self.game
+    }
+
+    /// Reload the scripts whenever their files change.
+    pub fn with_watch(mut self, watch: Watch) -> Self {
+        self.watch = Some(watch);
+        self
+    }
+
+    /// Take over the terminal and play until the player quits.
+    pub fn run(&mut self) -> crossterm::Result<()> {
+        terminal::enable_raw_mode()?;
+        queue!(self.output, EnterAlternateScreen, cursor::Hide)?;
+
+        let result = self.event_loop();
+
+        // Always try to give the terminal back, even if something failed.
+        queue!(self.output, cursor::Show, LeaveAlternateScreen)?;
+        self.output.flush()?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    fn event_loop(&mut self) -> crossterm::Result<()> {
+        loop {
+            self.tick();
+            self.draw()?;
+            // Wake up every so often to check the files if they are being watched, and to count
+            // down a timed choice
+            let interval = match (self.game.time_left(), &self.watch) {
+                (Some(_), _) => Some(TIMER_INTERVAL),
+                (None, Some(_)) => Some(WATCH_INTERVAL),
+                (None, None) => None,
+            };
+            if let Some(interval) = interval {
+                if !event::poll(interval)? {
+                    let message = self.watch.as_mut().and_then(|w| w.poll(&mut self.game));
+                    if let Some(message) = message {
+                        self.scroll = 0;
+                        self.reset_prompt();
+                        self.status = Some(message);
+                    }
+                    continue;
+                }
+            }
+            let event = event::read()?;
+            // A key pressed after the time ran out was meant for the choice that timed out
+            if self.tick() {
+                continue;
+            }
+            match event {
+                Event::Key(key) => {
+                    if !self.handle_key(key) {
+                        return Ok(());
+                    }
+                }
+                // We redraw on every event anyway
+                Event::Resize(_, _) | Event::Mouse(_) => {}
+            }
+        }
+    }
+
+    /// Pass the time since the last tick on to the game. Returns whether that ran out the time on
+    /// the choice being asked.
+    fn tick(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_tick).as_secs_f64();
+        self.last_tick = now;
+        match self.game.advance(elapsed) {
+            Ok(false) => false,
+            Ok(true) => {
+                self.scroll = 0;
+                self.reset_prompt();
+                self.status.get_or_insert_with(|| "Out of time!".to_owned());
+                true
+            }
+            // An error replaces the game with an error screen, so it is treated like moving on
+            Err(HeadlessError::Engine(_)) => {
+                self.scroll = 0;
+                self.reset_prompt();
+                true
+            }
+            Err(err) => {
+                self.status = Some(err.to_string());
+                false
+            }
+        }
+    }
+
+    /// Returns false if we should quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let is_ctrl_c =
+            key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+        if key.code == KeyCode::Esc || is_ctrl_c || !self.game.is_waiting() {
+            return false;
+        }
+
+        if key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            match self.game.undo() {
+                Ok(true) | Err(HeadlessError::Engine(_)) => {
+                    self.scroll = 0;
+                    self.reset_prompt();
+                }
+                Ok(false) => self.status = Some("Nothing to undo".to_owned()),
+                Err(err) => self.status = Some(err.to_string()),
+            }
+            return true;
+        }
+
+        match key.code {
+            KeyCode::PageUp => {
+                self.scroll = self.scroll.saturating_sub(self.text_height());
+                return true;
+            }
+            KeyCode::PageDown => {
+                self.scroll += self.text_height();
+                return true;
+            }
+            _ => {}
+        }
+
+        if let Some(choices) = self.game.choices() {
+            match key.code {
+                KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down => {
+                    self.selected = (self.selected + 1).min(choices.len().saturating_sub(1))
+                }
+                KeyCode::Enter => self.answer(|game, selected, _| game.choose(selected)),
+                KeyCode::Char(c) => {
+                    if let Some(index) = c.to_digit(10).filter(|&d| d >= 1) {
+                        let index = index as usize - 1;
+                        self.answer(|game, _, _| game.choose(index));
+                    }
+                }
+                _ => {}
+            }
+        } else if let Some(InputKind::Select(options)) = self.game.input_kind() {
+            let selected = match key.code {
+                KeyCode::Up => self.selected.saturating_sub(1),
+                KeyCode::Down => (self.selected + 1).min(options.len().saturating_sub(1)),
+                KeyCode::Enter => {
+                    self.answer(|game, _, line| game.submit(line));
+                    return true;
+                }
+                _ => return true,
+            };
+            if let Some(option) = options.get(selected) {
+                self.selected = selected;
+                self.line = option.clone();
+                self.cursor = self.line.chars().count();
+            }
+        } else {
+            let multiline = self.game.input_kind() == Some(InputKind::Multiline);
+            match key.code {
+                KeyCode::Enter if multiline && key.modifiers.contains(KeyModifiers::ALT) => {
+                    let at = self.byte_index(self.cursor);
+                    self.line.insert(at, '\n');
+                    self.cursor += 1;
+                    self.check();
+                }
+                KeyCode::Enter => self.answer(|game, _, line| game.submit(line)),
+                KeyCode::Char(c) => {
+                    let at = self.byte_index(self.cursor);
+                    self.line.insert(at, c);
+                    self.cursor += 1;
+                    self.check();
+                }
+                KeyCode::Backspace if self.cursor > 0 => {
+                    self.cursor -= 1;
+                    let at = self.byte_index(self.cursor);
+                    self.line.remove(at);
+                    self.check();
+                }
+                KeyCode::Delete if self.cursor < self.line.chars().count() => {
+                    let at = self.byte_index(self.cursor);
+                    self.line.remove(at);
+                    self.check();
+                }
+                KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+                KeyCode::Right => self.cursor = (self.cursor + 1).min(self.line.chars().count()),
+                KeyCode::Home => self.cursor = 0,
+                KeyCode::End => self.cursor = self.line.chars().count(),
+                _ => {}
+            }
+        }
+        true
+    }
+
+    /// Answer the current prompt, resetting the view if the game moved on.
+    fn answer<F>(&mut self, answer: F)
+    where
+        F: FnOnce(&Headless, usize, &str) -> Result<(), HeadlessError>,
+    {
+        let text = self.game.text();
+        match answer(&self.game, self.selected, &self.line) {
+            // An error replaces the game with an error screen, so it is treated like moving on
+            Ok(()) | Err(HeadlessError::Engine(_)) => {
+                if self.game.text() != text {
+                    self.scroll = 0;
+                }
+                self.reset_prompt();
+            }
+            Err(HeadlessError::InvalidInput { message, .. }) => self.status = Some(message),
+            Err(err) => self.status = Some(err.to_string()),
+        }
+    }
+
+    /// Show whether the line being edited would be accepted, if the game wants it checked as the
+    /// player types.
+    fn check(&mut self) {
+        match self.game.check(&self.line) {
+            Ok(None) => {}
+            Ok(Some(Ok(()))) => self.status = None,
+            Ok(Some(Err(message))) => self.status = Some(message),
+            // The validator failed, so the error screen is shown instead
+            Err(HeadlessError::Engine(_)) => {
+                self.scroll = 0;
+                self.reset_prompt();
+            }
+            Err(err) => self.status = Some(err.to_string()),
+        }
+    }
+
+    /// Set up for whatever the game is now asking.
+    fn reset_prompt(&mut self) {
+        self.selected = 0;
+        self.line = self.game.input().unwrap_or_default();
+        self.cursor = self.line.chars().count();
+        self.status = self.game.output();
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.line
+            .char_indices()
+            .nth(char_index)
+            .map_or(self.line.len(), |(index, _)| index)
+    }
+
+    /// The number of rows the prompt takes up.
+    fn prompt_height(&self) -> usize {
+        match self.game.choices() {
+            Some(choices) => choices.len(),
+            None if self.game.is_waiting() => 1,
+            None => 0,
+        }
+    }
+
+    /// The number of rows available for the text pane.
+    fn text_height(&self) -> usize {
+        let (_, height) = terminal::size().unwrap_or((80, 24));
+        // Title, two separators and the help line
+        (height as usize)
+            .saturating_sub(self.prompt_height() + 4)
+            .max(1)
+    }
+
+    /// Print the line from `wrap_markup`, at wherever the cursor is.
+    fn print_styled(&mut self, line: &[(char, usize)], styles: &[String]) -> crossterm::Result<()> {
+        // Print each stretch of the line that has the same style
+        let mut start = 0;
+        while start < line.len() {
+            let style = line[start].1;
+            let end = line[start..]
+                .iter()
+                .position(|&(_, other)| other != style)
+                .map_or(line.len(), |len| start + len);
+            let part: String = line[start..end].iter().map(|&(c, _)| c).collect();
+            if styles[style].is_empty() {
+                queue!(self.output, Print(part))?;
+            } else {
+                queue!(
+                    self.output,
+                    Print(&styles[style]),
+                    Print(part),
+                    Print(markup::ANSI_RESET)
+                )?;
+            }
+            start = end;
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self) -> crossterm::Result<()> {
+        let (width, height) = terminal::size()?;
+        let width = width as usize;
+        let text_height = self.text_height();
+        let separator = "-".repeat(width);
+
+        queue!(self.output, terminal::Clear(ClearType::All))?;
+
+        let title = truncate(&self.game.title(), width);
+        queue!(
+            self.output,
+            cursor::MoveTo(0, 0),
+            SetAttribute(Attribute::Bold),
+            Print(title),
+            SetAttribute(Attribute::Reset),
+            cursor::MoveTo(0, 1),
+            Print(&separator),
+        )?;
+
+        // The bars go in a sidebar to the right of the text, if there is room for one
+        let bars = self.game.bars();
+        let sidebar_width = if bars.is_empty() || width < MIN_WIDTH_FOR_SIDEBAR {
+            0
+        } else {
+            (width / 3).min(MAX_SIDEBAR_WIDTH)
+        };
+        let text_width = width - sidebar_width;
+
+        let (styles, lines) = wrap_markup(&self.game.text_markup(), text_width);
+        // Don't allow scrolling past the point where the last line is at the bottom
+        self.scroll = self.scroll.min(lines.len().saturating_sub(text_height));
+        for (row, line) in lines.iter().skip(self.scroll).take(text_height).enumerate() {
+            queue!(self.output, cursor::MoveTo(0, (row + 2) as u16))?;
+            self.print_styled(line, &styles)?;
+        }
+
+        if sidebar_width > 0 {
+            // Leave a column for the border
+            let (styles, lines) = wrap_markup(&bars_markup(&bars), sidebar_width - 2);
+            for row in 0..text_height {
+                queue!(
+                    self.output,
+                    cursor::MoveTo(text_width as u16, (row + 2) as u16),
+                    Print("| ")
+                )?;
+                if let Some(line) = lines.get(row) {
+                    self.print_styled(line, &styles)?;
+                }
+            }
+        }
+
+        let mut row = (text_height + 2) as u16;
+        queue!(self.output, cursor::MoveTo(0, row), Print(&separator))?;
+        row += 1;
+
+        if let Some(choices) = self.game.choices() {
+            for (index, choice) in choices.iter().enumerate() {
+                let text = truncate(&format!(" {}. {}", index + 1, choice), width);
+                queue!(self.output, cursor::MoveTo(0, row))?;
+                if index == self.selected {
+                    queue!(
+                        self.output,
+                        SetAttribute(Attribute::Reverse),
+                        Print(text),
+                        SetAttribute(Attribute::Reset)
+                    )?;
+                } else {
+                    queue!(self.output, Print(text))?;
+                }
+                row += 1;
+            }
+        } else if self.game.is_waiting() {
+            // Shown with a char in place of each of the line's, so that the cursor lines up
+            let line: String = match self.game.input_kind() {
+                Some(InputKind::Secret) => self.line.chars().map(|_| '*').collect(),
+                Some(InputKind::Multiline) => self.line.replace('\n', "\u{21b5}"),
+                Some(InputKind::Select(options)) => {
+                    format!("{} ({}/{})", self.line, self.selected + 1, options.len())
+                }
+                _ => self.line.clone(),
+            };
+            queue!(
+                self.output,
+                cursor::MoveTo(0, row),
+                Print("> "),
+                Print(line)
+            )?;
+            row += 1;
+        }
+
+        let help = match &self.status {
+            Some(status) => status.clone(),
+            None if self.game.choices().is_some() => match self.game.time_left() {
+                Some(left) => format!("{}s left  {}", left.ceil(), HELP_CHOICE),
+                None => HELP_CHOICE.to_owned(),
+            },
+            None if self.game.is_waiting() => match self.game.input_kind() {
+                Some(InputKind::Multiline) => HELP_MULTILINE.to_owned(),
+                Some(InputKind::Select(_)) => HELP_SELECT.to_owned(),
+                _ => HELP_INPUT.to_owned(),
+            },
+            None => HELP_ENDED.to_owned(),
+        };
+        queue!(
+            self.output,
+            cursor::MoveTo(0, height.saturating_sub(1).max(row)),
+            SetAttribute(Attribute::Dim),
+            Print(truncate(&help, width)),
+            SetAttribute(Attribute::Reset),
+        )?;
+
+        // Show the cursor in the line being edited
+        if self.game.is_waiting() && self.game.choices().is_none() {
+            let column = (self.cursor + 2).min(width.saturating_sub(1));
+            queue!(
+                self.output,
+                cursor::MoveTo(column as u16, row - 1),
+                cursor::Show
+            )?;
+        } else {
+            queue!(self.output, cursor::Hide)?;
+        }
+
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+/// Lay out the bars as markup for the sidebar.
+fn bars_markup(bars: &[RenderedBar]) -> String {
+    let mut result = String::new();
+    for bar in bars {
+        result.push_str(&format!("[b]{}[/b]\n", markup::escape(&bar.name)));
+        match &bar.content {
+            BarContent::Text(text) => result.push_str(text),
+            BarContent::Rows(rows) => {
+                for (label, value) in rows {
+                    if value.is_empty() {
+                        result.push_str(&format!("{}\n", label));
+                    } else {
+                        result.push_str(&format!("{}: {}\n", label, value));
+                    }
+                }
+            }
+        }
+        result.push_str("\n\n");
+    }
+    result
+}
+
+/// Parse the markup and word-wrap it.
+/// Each char is paired with the index of the ANSI codes for its style in the returned styles.
+fn wrap_markup(text: &str, width: usize) -> (Vec<String>, Vec<Vec<(char, usize)>>) {
+    let mut styles = Vec::new();
+    let mut chars = Vec::new();
+    for (run, style) in markup::runs(&markup::parse(text)) {
+        let index = styles.len();
+        styles.push(markup::ansi_codes(&style));
+        chars.extend(run.chars().map(|c| (c, index)));
+    }
+    (styles, wrap(&chars, width))
+}
+
+/// Cut the text down to at most `width` chars.
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Word-wrap the text into lines no longer than `width` chars, where each char carries some
+/// extra data such as its style.
+/// Newlines in the text are kept, and words longer than a line are split.
+fn wrap<T: Copy>(text: &[(char, T)], width: usize) -> Vec<Vec<(char, T)>> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for paragraph in text.split(|&(c, _)| c == '\n') {
+        let mut line = Vec::new();
+        // Where the current word starts in the paragraph
+        let mut start: usize = 0;
+        for word in paragraph.split(|&(c, _)| c == ' ') {
+            let mut word = word.to_vec();
+            // The space before this word, so that it keeps its style
+            let space = start.checked_sub(1).map(|index| paragraph[index]);
+            start += word.len() + 1;
+            // Not enough room for the word on this line, so move onto the next
+            if !line.is_empty() && line.len() + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if let (false, Some(space)) = (line.is_empty(), space) {
+                line.push(space);
+            }
+            // Too long for even an empty line
+            while word.len() > width {
+                let rest = word.split_off(width);
+                lines.push(word);
+                word = rest;
+            }
+            line.extend(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
@@ -0,0 +1,81 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use super::headless::{Headless, HeadlessError};
+use crate::RunOptions;
+
+/// How often the files are checked for changes while waiting on them.
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Reloads a game's scripts, and the string tables next to them, when their files change, so that
+/// edits can be seen without restarting. The files are polled by the terminal frontends, in
+/// between the player's answers.
+#[derive(Debug)]
+pub struct Watch {
+    files: Vec<PathBuf>,
+    /// Each file being watched, including the string tables, and when it was last modified, as
+    /// of the last time they were checked
+    modified: Vec<(PathBuf, Option<SystemTime>)>,
+    options: RunOptions,
+}
+impl Watch {
+    pub fn new<P: AsRef<Path>>(files: &[P], options: RunOptions) -> Self {
+        let files: Vec<PathBuf> = files.iter().map(|file| file.as_ref().to_owned()).collect();
+        let modified = watched(&files);
+        Self {
+            files,
+            modified,
+            options,
+        }
+    }
+
+    /// Reload the game if any of the files changed since the last time they were checked.
+    /// Returns a message for the player about what happened, if anything did.
+    pub fn poll(&mut self, game: &mut Headless) -> Option<String> {
+        let modified = watched(&self.files);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        let result = crate::load_files(&self.files)
+            .and_then(|sources| {
+                let locales = crate::load_locales(&crate::find_locales(&self.files))?;
+                Ok((sources, locales))
+            })
+            .map_err(HeadlessError::Reload)
+            .and_then(|(sources, locales)| {
+                let options = RunOptions {
+                    locales,
+                    ..self.options.clone()
+                };
+                game.reload(sources, &options)
+            });
+        Some(match result {
+            Ok(()) if game.diagnostics().is_empty() => "Reloaded".to_owned(),
+            Ok(()) => format!("Reloaded, with {} warnings", game.diagnostics().len()),
+            Err(err) => err.to_string(),
+        })
+    }
+}
+
+/// The files and the string tables next to them, with when each was last modified.
+fn watched(files: &[PathBuf]) -> Vec<(PathBuf, Option<SystemTime>)> {
+    files
+        .iter()
+        .cloned()
+        .chain(crate::find_locales(files))
+        .map(|file| {
+            let modified = modified(&file);
+            (file, modified)
+        })
+        .collect()
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
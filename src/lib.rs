@@ -1,11 +1,23 @@
-mod engine;
+pub mod analysis;
+pub mod diagnostics;
+pub mod engine;
+pub mod frontend;
+pub mod markup;
+pub mod playthrough;
 mod util;
 mod wasm;
 
-use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
 
+use diagnostics::Diagnostic;
+use engine::error::EngineError;
 use rune::EmitDiagnostics;
 
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
@@ -14,13 +26,33 @@ use wasm_bindgen::prelude::*;
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
-fn create_rune(sources: &mut rune::Sources) -> Result<runestick::Vm, String> {
+/// Settings for compiling and starting a game.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Refuse to start if there are any warnings, rather than only reporting them.
+    /// Useful for CI, so that warnings don't go unnoticed.
+    pub warnings_as_errors: bool,
+    /// The string tables that `state.tr` looks text up in. The first is used for any keys that
+    /// are missing in the others.
+    pub locales: Vec<engine::locale::StringTable>,
+    /// The language to start in. Defaults to that of the first string table.
+    pub language: Option<String>,
+}
+
+/// Compile the sources, returning the warnings alongside the vm.
+/// Anything the scripts print goes to `output`.
+fn create_rune(
+    sources: &mut rune::Sources,
+    options: &RunOptions,
+    support: frontend::Support,
+    output: &wasm::rune_core::Output,
+) -> Result<(runestick::Vm, Vec<Diagnostic>), EngineError> {
     let mut warnings = rune::Warnings::new();
     let mut errors = rune::Errors::new();
 
-    let context = match create_rune_context() {
+    let context = match create_rune_context(support, output) {
         Ok(context) => context,
-        Err(err) => return Err(format!("{}", err)),
+        Err(err) => return Err(EngineError::Compile(err.to_string())),
     };
 
     let unit = rune::load_sources(
@@ -41,13 +73,13 @@ fn create_rune(sources: &mut rune::Sources) -> Result<runestick::Vm, String> {
             String::from_utf8(writer.into_inner()).expect("Expected diagnostics to be valid utf8");
         let new_len = string.trim_end().len();
         string.truncate(new_len);
-        return Err(string);
+        return Err(EngineError::Compile(string));
     // let mut error_string = "Rune Errors: ".to_owned();
     // for error in errors {
     //     error_string.push_str(format!("{}\n", error.to_string()).as_str());
     // }
     // return Err(error_string);
-    } else if !warnings.is_empty() {
+    } else if options.warnings_as_errors && !warnings.is_empty() {
         warnings
             .emit_diagnostics(&mut writer, &sources)
             .expect("emitting to buffer should not fail");
@@ -55,7 +87,7 @@ fn create_rune(sources: &mut rune::Sources) -> Result<runestick::Vm, String> {
             String::from_utf8(writer.into_inner()).expect("Expected diagnostics to be valid utf8");
         let new_len = string.trim_end().len();
         string.truncate(new_len);
-        return Err(string);
+        return Err(EngineError::Compile(string));
         // let mut warning_string = "Rune Warnings: ".to_owned();
         // for warning in &warnings {
         //     warning_string.push_str(format!("{}\n", warning).as_str());
@@ -63,23 +95,208 @@ fn create_rune(sources: &mut rune::Sources) -> Result<runestick::Vm, String> {
         // return Err(warning_string);
     }
 
+    let diagnostics = diagnostics::from_warnings(&warnings, sources);
+
     let unit = unit.unwrap();
 
     let vm = runestick::Vm::new(Arc::new(context), Arc::new(unit));
 
-    Ok(vm)
+    Ok((vm, diagnostics))
 }
-fn create_rune_context() -> Result<runestick::Context, runestick::ContextError> {
+fn create_rune_context(
+    support: frontend::Support,
+    output: &wasm::rune_core::Output,
+) -> Result<runestick::Context, runestick::ContextError> {
     let mut context = runestick::Context::with_config(false)?;
-    context.install(&wasm::rune_core::create_module()?)?;
+    context.install(&wasm::rune_core::create_module(output)?)?;
+    #[cfg(target_arch = "wasm32")]
     context.install(&wasm::rune_lib::create_module()?)?;
-    context.install(&engine::create_module()?)?;
+    context.install(&engine::create_module(support)?)?;
+    context.install(&markup::create_module()?)?;
     for module in util::create_modules()?.iter() {
         context.install(&module)?;
     }
     Ok(context)
 }
 
+/// Compile the sources and call their `entry` function with a new `State` that displays through
+/// the given frontend, and keeps save slots in the given storage.
+/// Any error is also shown on the frontend. Warnings from compiling are returned so that the
+/// host can report them, as the game still runs despite them, along with a handle for acting on
+/// the game from the host.
+pub fn run_entry(
+    sources: &mut rune::Sources,
+    frontend: Rc<dyn frontend::Frontend>,
+    storage: Rc<dyn engine::storage::SaveStorage>,
+    options: &RunOptions,
+) -> Result<(engine::Handle, Vec<Diagnostic>), EngineError> {
+    run_entry_with_output(
+        sources,
+        frontend,
+        storage,
+        options,
+        wasm::rune_core::Output::default(),
+    )
+}
+
+/// `run_entry`, with what the scripts print going to `output`, so that it can still be read if
+/// the game fails to start.
+fn run_entry_with_output(
+    sources: &mut rune::Sources,
+    frontend: Rc<dyn frontend::Frontend>,
+    storage: Rc<dyn engine::storage::SaveStorage>,
+    options: &RunOptions,
+    output: wasm::rune_core::Output,
+) -> Result<(engine::Handle, Vec<Diagnostic>), EngineError> {
+    let result =
+        create_rune(sources, options, frontend.support(), &output).and_then(|(vm, diagnostics)| {
+            let mut state = engine::State::new(frontend.clone(), storage, vm.unit().clone());
+            state.locale =
+                engine::locale::Locale::new(options.locales.clone(), options.language.clone());
+            state.output = output;
+            state.replay.borrow_mut()?.language = state.locale.language();
+            let handle = engine::Handle::new(state.clone());
+
+            vm.call(&["entry"], (state.clone(),))
+                .map_err(|err| EngineError::script(None, err))?;
+            // Enter the scene that `entry` went to, if any.
+            state.transitions.run(&state)?;
+            engine::refresh_bars(&state.bars, &state.info, &*frontend)?;
+            Ok((handle, diagnostics))
+        });
+    engine::report_error(&*frontend, result)
+}
+
+/// Recompile the sources and swap them into the running game, keeping its info and the scene it
+/// is in, which is entered again with the new scripts. See `engine::Handle::reload`.
+/// Compile errors are only returned, and not shown on the frontend, so that the game carries on
+/// as it was until the scripts are fixed.
+pub fn reload(
+    handle: &mut engine::Handle,
+    sources: &mut rune::Sources,
+    options: &RunOptions,
+) -> Result<Vec<Diagnostic>, EngineError> {
+    let (vm, diagnostics) = create_rune(sources, options, handle.support(), handle.output())?;
+    handle.reload(vm, options.locales.clone())?;
+    Ok(diagnostics)
+}
+
+/// Compile the sources without running them, and look for mistakes in how the scenes are
+/// connected. Returns the warnings from compiling along with those about the scenes.
+pub fn check(
+    sources: &mut rune::Sources,
+    options: &RunOptions,
+) -> Result<Vec<Diagnostic>, EngineError> {
+    let (graph, mut diagnostics) = scene_graph(sources, options)?;
+    diagnostics.extend(graph.check());
+    Ok(diagnostics)
+}
+
+/// Compile the sources without running them, and find how the scenes are connected.
+/// The warnings from compiling are returned alongside it.
+pub fn scene_graph(
+    sources: &mut rune::Sources,
+    options: &RunOptions,
+) -> Result<(analysis::SceneGraph, Vec<Diagnostic>), EngineError> {
+    let (vm, diagnostics) = create_rune(
+        sources,
+        options,
+        frontend::Support::default(),
+        &wasm::rune_core::Output::default(),
+    )?;
+    let graph = analysis::SceneGraph::from_unit(vm.unit(), sources);
+    Ok((graph, diagnostics))
+}
+
+/// Read each of the files into sources, named by their path.
+pub fn load_files<P: AsRef<Path>>(paths: &[P]) -> Result<rune::Sources, EngineError> {
+    let mut sources = rune::Sources::new();
+    for path in paths {
+        let path = path.as_ref();
+        let code = std::fs::read_to_string(path).map_err(|err| EngineError::SourceFetch {
+            source: path.display().to_string(),
+            status: None,
+            error: err.to_string(),
+        })?;
+        sources.insert(runestick::Source::new(path.display().to_string(), code));
+    }
+    Ok(sources)
+}
+
+/// The string tables (`.lang` files) in the directories of the files, such as the sources of a
+/// game, in the order of their names.
+pub fn find_locales<P: AsRef<Path>>(files: &[P]) -> Vec<PathBuf> {
+    let mut directories: Vec<&Path> = files
+        .iter()
+        .map(|file| file.as_ref().parent().unwrap_or_else(|| Path::new("")))
+        .collect();
+    directories.dedup();
+    let mut locales: Vec<PathBuf> = directories
+        .into_iter()
+        .filter_map(|directory| {
+            let directory = if directory.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                directory
+            };
+            std::fs::read_dir(directory).ok()
+        })
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |extension| extension == "lang")
+        })
+        .collect();
+    locales.sort();
+    locales.dedup();
+    locales
+}
+
+/// Read each of the string tables, named by the file name without its extension, such as `en`
+/// for `en.lang`.
+pub fn load_locales<P: AsRef<Path>>(
+    paths: &[P],
+) -> Result<Vec<engine::locale::StringTable>, EngineError> {
+    paths
+        .iter()
+        .map(|path| {
+            let path = path.as_ref();
+            let text = std::fs::read_to_string(path).map_err(|err| EngineError::SourceFetch {
+                source: path.display().to_string(),
+                status: None,
+                error: err.to_string(),
+            })?;
+            let language = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            engine::locale::StringTable::parse(&language, &text).map_err(|err| {
+                EngineError::Config(format!("In string table '{}', {}", path.display(), err))
+            })
+        })
+        .collect()
+}
+
+/// Turn code that is already in memory into sources, as pairs of `(name, code)`.
+/// This is the native equivalent of the `sources` startup option on the web.
+pub fn load_inline<N: AsRef<str>, C: AsRef<str>>(inline_sources: &[(N, C)]) -> rune::Sources {
+    let mut sources = rune::Sources::new();
+    for (name, code) in inline_sources {
+        sources.insert(runestick::Source::new(name.as_ref(), code.as_ref()));
+    }
+    sources
+}
+
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    /// The game that `start` started, and the options it was started with, for `reload`.
+    static GAME: std::cell::RefCell<Option<(engine::Handle, RunOptions)>> =
+        std::cell::RefCell::new(None);
+}
+
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub async fn start(info: JsValue) -> Result<JsValue, JsValue> {
     // Provide better panic information.
@@ -95,45 +312,149 @@ pub async fn start(info: JsValue) -> Result<JsValue, JsValue> {
         return Err(JsValue::from_str("Failure, info was not an object."));
     }
 
-    let info = engine::UserInfo::from_js_object(info)
+    // There is nowhere to show an error screen if this fails, so it only goes to the host.
+    let info = match wasm::user_info::UserInfo::from_js_object(info).await {
+        Ok(info) => info,
+        Err(err) => {
+            log_info(&err.to_string());
+            return Err(wasm::util::engine_error_to_js(&err));
+        }
+    };
+    let mut sources = info.sources.sources;
+
+    let output = wasm::rune_core::Output::default();
+    let result = run_entry_with_output(
+        &mut sources,
+        Rc::new(info.frontend),
+        Rc::new(info.storage),
+        &info.options,
+        output.clone(),
+    )
+    .map(|(handle, diagnostics)| {
+        GAME.with(|game| *game.borrow_mut() = Some((handle, info.options.clone())));
+        diagnostics
+    });
+
+    if let Some(output) = output.drain() {
+        log(output.as_str());
+    }
+
+    // The warnings are given back so that the page can show them, such as in a developer panel
+    result
+        .map(|diagnostics| {
+            for diagnostic in diagnostics.iter() {
+                web_sys::console::warn_1(&JsValue::from_str(&diagnostic.to_string()));
+            }
+            wasm::util::diagnostics_to_js(&diagnostics)
+        })
+        .map_err(|err| wasm::util::engine_error_to_js(&err))
+}
+
+/// Swap new scripts into the game started by `start`, keeping the info and the scene it is in.
+/// `info` gives the sources in the same way as for `start`, with `files` and `sources`, and can
+/// give new string tables with `locales`, otherwise those the game started with are kept.
+/// Errors are returned without disturbing the game, so that a page can keep trying as the
+/// scripts are edited.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub async fn reload(info: JsValue) -> Result<JsValue, JsValue> {
+    let mut sources = wasm::user_info::SourceUserInfo::try_from_js_value(&info)
+        .await
+        .map_err(|err| wasm::util::engine_error_to_js(&err))?
+        .sources;
+    let locales = wasm::user_info::UserInfo::load_locales(&info)
         .await
-        .expect("Info passed into start was not of correct format");
+        .map_err(|err| wasm::util::engine_error_to_js(&err))?;
 
-    let mut state = engine::State::new(info);
+    let result = GAME.with(|game| match &mut *game.borrow_mut() {
+        Some((handle, game_options)) => {
+            let mut options = game_options.clone();
+            if let Some(locales) = locales {
+                options.locales = locales;
+            }
+            let result = reload(handle, &mut sources, &options);
+            // The new string tables are only kept if they were swapped in
+            if result.is_ok() {
+                *game_options = options;
+            }
+            result
+        }
+        None => Err(EngineError::Config(
+            "There is no game to reload, `start` must finish first".to_owned(),
+        )),
+    });
 
-    let vm = create_rune(&mut state.uinfo.sources.sources)?;
-    log_info("Created virtual machine");
+    log_game_output();
 
-    let _result = match vm.call(&["entry"], (state,)) {
-        Ok(value) => value,
-        Err(err) => return Err(format!("[VMError::entry]: {}", err).into()),
-    };
+    result
+        .map(|diagnostics| {
+            for diagnostic in diagnostics.iter() {
+                web_sys::console::warn_1(&JsValue::from_str(&diagnostic.to_string()));
+            }
+            wasm::util::diagnostics_to_js(&diagnostics)
+        })
+        .map_err(|err| {
+            log_info(&err.to_string());
+            wasm::util::engine_error_to_js(&err)
+        })
+}
 
-    if let Some(output) = wasm::rune_core::drain_output() {
-        log(output.as_str());
-    }
+/// Undo the last choice in the game started by `start`, for the player, like `:undo` in
+/// the terminal. Returns whether there was anything to undo.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn undo() -> Result<bool, JsValue> {
+    let result = GAME.with(|game| match &*game.borrow() {
+        Some((handle, _)) => handle.undo(),
+        None => Err(EngineError::Config(
+            "There is no game to undo in, `start` must finish first".to_owned(),
+        )),
+    });
 
-    Ok(JsValue::null())
+    log_game_output();
+
+    result.map_err(|err| wasm::util::engine_error_to_js(&err))
 }
 
-fn clear_element(element: web_sys::Element) {
-    element.set_inner_html("");
+/// Show the game started by `start` in another language, which needs a string table given in
+/// `locales` when starting.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn set_language(language: &str) -> Result<(), JsValue> {
+    let result = GAME.with(|game| match &*game.borrow() {
+        Some((handle, _)) => handle.set_language(language),
+        None => Err(EngineError::Config(
+            "There is no game to change the language of, `start` must finish first".to_owned(),
+        )),
+    });
+
+    log_game_output();
+
+    result.map_err(|err| wasm::util::engine_error_to_js(&err))
 }
 
+/// Log what the scripts of the game started by `start` printed.
+#[cfg(target_arch = "wasm32")]
+fn log_game_output() {
+    let output = GAME.with(|game| match &*game.borrow() {
+        Some((handle, _)) => handle.drain_output(),
+        None => None,
+    });
+    if let Some(output) = output {
+        log(output.as_str());
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
 pub fn log_info(text: &str) {
     log2("[Aleph-Naught]", text);
 }
 
+#[cfg(target_arch = "wasm32")]
 pub fn log(text: &str) {
     web_sys::console::log_1(&JsValue::from_str(text));
 }
+#[cfg(target_arch = "wasm32")]
 pub fn log2(prefix: &str, text: &str) {
     web_sys::console::log_2(&JsValue::from_str(prefix), &JsValue::from_str(text));
 }
-
-pub fn handle_vm_result<V>(v: Result<V, runestick::VmError>) -> V {
-    match v {
-        Ok(v) => v,
-        Err(err) => panic!("[VMError]: {}", err),
-    }
-}
@@ -0,0 +1,471 @@
+//! A small markup for styling text, which each backend renders in its own way.
+//! Tags are written in square brackets and closed with a slash:
+//! `[b]bold[/b]`, `[i]italic[/i]`, `[color=red]red[/color]`, `[h]heading[/h]`, and `[br]` for a
+//! line break (as well as normal newlines).
+//! Images are written as `[img=path/to/image.png]alt text[/img]`, where the alt text is shown by
+//! backends that can't display images. The alt text can't contain markup.
+//! A literal `[` is written as `[[`. Tags which aren't understood are left as they were written.
+use runestick::{ContextError, Module};
+
+pub const MODULE_NAME: &str = "Markup";
+
+pub fn create_module() -> Result<Module, ContextError> {
+    let mut module = Module::new(&[MODULE_NAME]);
+    module.function(&["escape"], |text: &str| escape(text))?;
+    module.function(&["plain"], |text: &str| to_plain(&parse(text)))?;
+    module.function(&["image"], |src: &str, alt: &str| image(src, alt))?;
+    Ok(module)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Style {
+    Bold,
+    Italic,
+    Heading,
+    /// A color name like `red`, or a hex color like `#ff0000`
+    Color(String),
+}
+impl Style {
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "b" => Some(Self::Bold),
+            "i" => Some(Self::Italic),
+            "h" => Some(Self::Heading),
+            _ => {
+                let color = tag.strip_prefix("color=")?;
+                // Only allow characters which can't break out of where the color is put
+                let is_valid = !color.is_empty()
+                    && color.chars().all(|c| c.is_ascii_alphanumeric() || c == '#');
+                if is_valid {
+                    Some(Self::Color(color.to_owned()))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// The name used in the closing tag
+    fn tag_name(&self) -> &'static str {
+        match self {
+            Self::Bold => "b",
+            Self::Italic => "i",
+            Self::Heading => "h",
+            Self::Color(_) => "color",
+        }
+    }
+}
+
+/// A piece of parsed text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Span {
+    Text(String),
+    LineBreak,
+    Styled { style: Style, children: Vec<Span> },
+    Image { src: String, alt: String },
+}
+
+/// Make the text display exactly as written, such as a name the player entered.
+pub fn escape(text: &str) -> String {
+    text.replace('[', "[[")
+}
+
+/// The markup for an image, or `None` if the path can't be used in markup.
+pub fn image(src: &str, alt: &str) -> Option<String> {
+    if is_valid_image_src(src) {
+        Some(format!("[img={}]{}[/img]", src, escape(alt)))
+    } else {
+        None
+    }
+}
+
+/// Only allow characters that can be in a path or url, and can't break out of an attribute.
+fn is_valid_image_src(src: &str) -> bool {
+    !src.is_empty()
+        && src
+            .chars()
+            .all(|c| c.is_alphanumeric() || "/._-~:%?&=#+,".contains(c))
+}
+
+/// Parse the markup into spans. This never fails, as anything which isn't valid markup is kept as
+/// text. Tags left open are closed at the end of the text.
+pub fn parse(text: &str) -> Vec<Span> {
+    // The spans of each open tag, with the root at the bottom
+    let mut stack: Vec<(Option<Style>, Vec<Span>)> = vec![(None, Vec::new())];
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let (_, spans) = stack.last_mut().expect("root is never popped");
+        if c == '\n' {
+            spans.push(Span::LineBreak);
+            rest = &rest[1..];
+            continue;
+        } else if c != '[' {
+            push_char(spans, c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+
+        if rest.starts_with("[[") {
+            push_char(spans, '[');
+            rest = &rest[2..];
+            continue;
+        }
+
+        let tag = rest[1..].find(']').map(|end| &rest[1..end + 1]);
+        let handled = match tag {
+            Some("br") => {
+                spans.push(Span::LineBreak);
+                true
+            }
+            Some(tag) if tag.starts_with('/') => {
+                let is_open = matches!(
+                    stack.last(),
+                    Some((Some(style), _)) if style.tag_name() == &tag[1..]
+                );
+                if is_open {
+                    let (style, children) = stack.pop().expect("checked above");
+                    let style = style.expect("checked above");
+                    let (_, parent) = stack.last_mut().expect("root is never popped");
+                    parent.push(Span::Styled { style, children });
+                }
+                is_open
+            }
+            Some(tag) if tag.starts_with("img=") && is_valid_image_src(&tag[4..]) => {
+                // The alt text is everything up to the closing tag, taken as it was written
+                let mut alt = String::new();
+                rest = &rest[tag.len() + 2..];
+                while let Some(c) = rest.chars().next() {
+                    if rest.starts_with("[[") {
+                        alt.push('[');
+                        rest = &rest[2..];
+                    } else if rest.starts_with("[/img]") {
+                        rest = &rest["[/img]".len()..];
+                        break;
+                    } else {
+                        alt.push(c);
+                        rest = &rest[c.len_utf8()..];
+                    }
+                }
+                spans.push(Span::Image {
+                    src: tag[4..].to_owned(),
+                    alt,
+                });
+                continue;
+            }
+            Some(tag) => match Style::from_tag(tag) {
+                Some(style) => {
+                    stack.push((Some(style), Vec::new()));
+                    true
+                }
+                None => false,
+            },
+            None => false,
+        };
+
+        if let (true, Some(tag)) = (handled, tag) {
+            rest = &rest[tag.len() + 2..];
+        } else {
+            let (_, spans) = stack.last_mut().expect("root is never popped");
+            push_char(spans, '[');
+            rest = &rest[1..];
+        }
+    }
+
+    // Close anything left open
+    while stack.len() > 1 {
+        let (style, children) = stack.pop().expect("checked length");
+        let (_, parent) = stack.last_mut().expect("checked length");
+        parent.push(Span::Styled {
+            style: style.expect("only the root has no style"),
+            children,
+        });
+    }
+    stack.pop().map(|(_, spans)| spans).unwrap_or_default()
+}
+
+fn push_char(spans: &mut Vec<Span>, c: char) {
+    match spans.last_mut() {
+        Some(Span::Text(text)) => text.push(c),
+        _ => spans.push(Span::Text(c.to_string())),
+    }
+}
+
+/// Every style applying to a piece of text, for backends which can't nest.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub heading: bool,
+    pub color: Option<String>,
+}
+impl TextStyle {
+    fn with(&self, style: &Style) -> Self {
+        let mut result = self.clone();
+        match style {
+            Style::Bold => result.bold = true,
+            Style::Italic => result.italic = true,
+            Style::Heading => result.heading = true,
+            Style::Color(color) => result.color = Some(color.clone()),
+        }
+        result
+    }
+}
+
+/// Flatten the spans into runs of text that share the same style. Line breaks become `\n`.
+pub fn runs(spans: &[Span]) -> Vec<(String, TextStyle)> {
+    fn add(spans: &[Span], style: &TextStyle, runs: &mut Vec<(String, TextStyle)>) {
+        for span in spans {
+            match span {
+                Span::Text(text) => push_run(runs, text, style),
+                Span::LineBreak => push_run(runs, "\n", style),
+                // Images are shown as their alt text when only text can be shown
+                Span::Image { alt, .. } => push_run(runs, alt, style),
+                Span::Styled {
+                    style: inner,
+                    children,
+                } => add(children, &style.with(inner), runs),
+            }
+        }
+    }
+    let mut runs = Vec::new();
+    add(spans, &TextStyle::default(), &mut runs);
+    runs
+}
+
+fn push_run(runs: &mut Vec<(String, TextStyle)>, text: &str, style: &TextStyle) {
+    match runs.last_mut() {
+        Some((last, last_style)) if last_style == style => last.push_str(text),
+        _ => runs.push((text.to_owned(), style.clone())),
+    }
+}
+
+/// Just the text, without any styling.
+pub fn to_plain(spans: &[Span]) -> String {
+    runs(spans).into_iter().map(|(text, _)| text).collect()
+}
+
+pub const ANSI_RESET: &str = "\x1b[0m";
+
+/// The ANSI escape that switches to the style. Empty for the default style.
+pub fn ansi_codes(style: &TextStyle) -> String {
+    let mut codes = Vec::new();
+    if style.bold || style.heading {
+        codes.push("1".to_owned());
+    }
+    if style.italic {
+        codes.push("3".to_owned());
+    }
+    if style.heading {
+        codes.push("4".to_owned());
+    }
+    if let Some(color) = style.color.as_deref().and_then(ansi_color) {
+        codes.push(color);
+    }
+    if codes.is_empty() {
+        String::new()
+    } else {
+        format!("\x1b[{}m", codes.join(";"))
+    }
+}
+
+fn ansi_color(color: &str) -> Option<String> {
+    let code = match color.to_ascii_lowercase().as_str() {
+        "black" => "30",
+        "red" => "31",
+        "green" => "32",
+        "yellow" => "33",
+        "blue" => "34",
+        "magenta" | "purple" => "35",
+        "cyan" => "36",
+        "white" => "37",
+        "gray" | "grey" => "90",
+        hex => {
+            let hex = hex.strip_prefix('#')?;
+            if hex.len() != 6 {
+                return None;
+            }
+            let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+            return Some(format!(
+                "38;2;{};{};{}",
+                channel(0)?,
+                channel(2)?,
+                channel(4)?
+            ));
+        }
+    };
+    Some(code.to_owned())
+}
+
+/// The text with ANSI escapes for terminals.
+pub fn to_ansi(spans: &[Span]) -> String {
+    let mut result = String::new();
+    for (text, style) in runs(spans) {
+        let codes = ansi_codes(&style);
+        if codes.is_empty() {
+            result.push_str(&text);
+        } else {
+            result.push_str(&codes);
+            result.push_str(&text);
+            result.push_str(ANSI_RESET);
+        }
+    }
+    result
+}
+
+/// The text as HTML, with everything that isn't markup escaped.
+pub fn to_html(spans: &[Span]) -> String {
+    let mut result = String::new();
+    for span in spans {
+        match span {
+            Span::Text(text) => push_html_escaped(&mut result, text),
+            Span::LineBreak => result.push_str("<br>"),
+            Span::Image { src, alt } => {
+                result.push_str("<img src=\"");
+                push_html_escaped(&mut result, src);
+                result.push_str("\" alt=\"");
+                push_html_escaped(&mut result, alt);
+                result.push_str("\">");
+            }
+            Span::Styled { style, children } => {
+                let children = to_html(children);
+                match style {
+                    Style::Bold => result.push_str(&format!("<b>{}</b>", children)),
+                    Style::Italic => result.push_str(&format!("<i>{}</i>", children)),
+                    Style::Heading => result.push_str(&format!("<h3>{}</h3>", children)),
+                    // The color was checked to only contain alphanumerics and '#' when parsing
+                    Style::Color(color) => result.push_str(&format!(
+                        "<span style=\"color: {}\">{}</span>",
+                        color, children
+                    )),
+                }
+            }
+        }
+    }
+    result
+}
+
+fn push_html_escaped(result: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> Span {
+        Span::Text(text.to_owned())
+    }
+
+    fn styled(style: Style, children: Vec<Span>) -> Span {
+        Span::Styled { style, children }
+    }
+
+    #[test]
+    fn parse_styles() {
+        assert_eq!(
+            parse("a [b]bold [i]both[/i][/b] [color=#ff0000]red[/color]"),
+            vec![
+                text("a "),
+                styled(
+                    Style::Bold,
+                    vec![text("bold "), styled(Style::Italic, vec![text("both")])]
+                ),
+                text(" "),
+                styled(Style::Color("#ff0000".to_owned()), vec![text("red")]),
+            ]
+        );
+        assert_eq!(
+            parse("one[br]two\nthree"),
+            vec![
+                text("one"),
+                Span::LineBreak,
+                text("two"),
+                Span::LineBreak,
+                text("three"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_escape() {
+        assert_eq!(parse("[[b]not bold"), vec![text("[b]not bold")]);
+        assert_eq!(parse("[[[[ ]]"), vec![text("[[ ]]")]);
+        for written in &["[b]Sam", "[[", "a [/b] [img=x.png]y[/img]", "[color=red]"] {
+            assert_eq!(to_plain(&parse(&escape(written))), *written);
+        }
+    }
+
+    #[test]
+    fn parse_unclosed() {
+        // Closed at the end of the text
+        assert_eq!(
+            parse("[b]bold"),
+            vec![styled(Style::Bold, vec![text("bold")])]
+        );
+        // A closing tag for something that isn't open is kept as text
+        assert_eq!(
+            parse("[b]a[/i]b"),
+            vec![styled(Style::Bold, vec![text("a[/i]b")])]
+        );
+        assert_eq!(parse("x[/b]"), vec![text("x[/b]")]);
+        // No `]` at all
+        assert_eq!(parse("[b bold"), vec![text("[b bold")]);
+        assert_eq!(parse("["), vec![text("[")]);
+    }
+
+    #[test]
+    fn parse_unknown() {
+        assert_eq!(parse("[u]x[/u]"), vec![text("[u]x[/u]")]);
+        assert_eq!(parse("[color=]x"), vec![text("[color=]x")]);
+        // Colors can't contain anything that could break out of the html attribute
+        assert_eq!(
+            parse("[color=red\"]x[/color]"),
+            vec![text("[color=red\"]x[/color]")]
+        );
+    }
+
+    #[test]
+    fn parse_image() {
+        assert_eq!(
+            parse("[img=images/door.png]A [[red] door[/img]!"),
+            vec![
+                Span::Image {
+                    src: "images/door.png".to_owned(),
+                    alt: "A [red] door".to_owned(),
+                },
+                text("!"),
+            ]
+        );
+        // Without a path it isn't an image
+        assert_eq!(parse("[img=]x[/img]"), vec![text("[img=]x[/img]")]);
+        assert_eq!(parse("[img=a\"b]x"), vec![text("[img=a\"b]x")]);
+        assert_eq!(image("", "alt"), None);
+        assert_eq!(
+            image("door.png", "[b]"),
+            Some("[img=door.png][[b][/img]".to_owned())
+        );
+    }
+
+    #[test]
+    fn render() {
+        let spans = parse("[b]<bold>[/b] & [img=a.png]\"alt\"[/img]");
+        assert_eq!(
+            to_html(&spans),
+            "<b>&lt;bold&gt;</b> &amp; <img src=\"a.png\" alt=\"&quot;alt&quot;\">"
+        );
+        assert_eq!(to_plain(&spans), "<bold> & \"alt\"");
+        assert_eq!(
+            to_ansi(&parse("[color=green]ok[/color] done")),
+            "\x1b[32mok\x1b[0m done"
+        );
+    }
+}
@@ -0,0 +1,513 @@
+//! Scripted playthroughs of a game, which pin down how its scenes behave so that changes to the
+//! scripts which break them are noticed. These are run with `aleph test`.
+//!
+//! A playthrough is a text file with one step per line. Blank lines, and lines starting with
+//! `#`, are skipped.
+//! ```text
+//! source demon.rune
+//! expect scene town
+//! choose Enter Tavern
+//! expect title Cheap Tavern
+//! choose Talk to the Demon
+//! expect text amiable conversation
+//! expect info.shoe_quest == true
+//! ```
+//! - `source <path>`: a source of the game, relative to the playthrough. These come first.
+//! - `choose <text>`: pick the choice with the text.
+//! - `input <text>`: submit the text to the input being asked for.
+//! - `undo`: undo the last choice.
+//! - `wait <seconds>`: let time pass, which runs out the time on a timed choice if it is longer
+//!   than the time left. No time passes otherwise, however long the game takes to run.
+//! - `expect scene <name>`: the game is in the scene.
+//! - `expect title <text>`, `expect text <text>`: the title or text contains the text, ignoring
+//!   any markup.
+//! - `expect choice <text>`: a choice with the text is being asked.
+//! - `expect info.<path> == <value>`: the value in the info, written like the Rune literal for
+//!   it, such as `true`, `5` or `"Bob"`. Fields of objects and structs are separated by `.`.
+//!
+//! Expectations that fail don't stop the playthrough, but a step that can't be done does, as the
+//! rest of the steps would be played from the wrong place.
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{engine::save::SaveValue, frontend::headless::Headless};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlaythroughError {
+    /// The playthrough file couldn't be read.
+    Read(String),
+    /// A line of the playthrough isn't a step that is understood.
+    Parse { line: usize, message: String },
+}
+impl std::fmt::Display for PlaythroughError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Read(err) => write!(f, "Failed to read playthrough: {}", err),
+            Self::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expectation {
+    Scene(String),
+    /// The title contains the text
+    Title(String),
+    /// The text contains the text
+    Text(String),
+    /// A choice with the text is being asked
+    Choice(String),
+    /// The value in the info at the path displays as the text
+    Info {
+        path: Vec<String>,
+        value: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepKind {
+    Choose(String),
+    Input(String),
+    Undo,
+    /// Let the seconds pass on the game's clock
+    Wait(f64),
+    Expect(Expectation),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// The line it was on, starting at 1
+    pub line: usize,
+    /// The line as it was written, for reporting
+    pub text: String,
+    pub kind: StepKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Playthrough {
+    pub sources: Vec<PathBuf>,
+    pub steps: Vec<Step>,
+}
+impl Playthrough {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PlaythroughError> {
+        let path = path.as_ref();
+        let text =
+            std::fs::read_to_string(path).map_err(|err| PlaythroughError::Read(err.to_string()))?;
+        Self::parse(&text, path.parent().unwrap_or_else(|| Path::new("")))
+    }
+
+    /// Parse the steps, with sources relative to `directory`.
+    pub fn parse(text: &str, directory: &Path) -> Result<Self, PlaythroughError> {
+        let mut sources = Vec::new();
+        let mut steps = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: &str| PlaythroughError::Parse {
+                line: line_number,
+                message: message.to_owned(),
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (command, argument) = match line.find(' ') {
+                Some(index) => (&line[..index], line[index + 1..].trim()),
+                None => (line, ""),
+            };
+            let kind = match command {
+                "source" if !steps.is_empty() => {
+                    return Err(error("Sources must come before any other step"))
+                }
+                "source" if !argument.is_empty() => {
+                    sources.push(directory.join(argument));
+                    continue;
+                }
+                "choose" if !argument.is_empty() => StepKind::Choose(argument.to_owned()),
+                "input" => StepKind::Input(argument.to_owned()),
+                "undo" if argument.is_empty() => StepKind::Undo,
+                "wait" => match argument.parse::<f64>() {
+                    Ok(seconds) if seconds >= 0.0 => StepKind::Wait(seconds),
+                    _ => return Err(error("Expected a number of seconds for 'wait'")),
+                },
+                "expect" => StepKind::Expect(parse_expectation(argument).map_err(error)?),
+                "source" | "choose" | "undo" => {
+                    return Err(error(&format!("Bad argument for '{}'", command)))
+                }
+                _ => return Err(error(&format!("Unknown step '{}'", command))),
+            };
+            steps.push(Step {
+                line: line_number,
+                text: line.to_owned(),
+                kind,
+            });
+        }
+
+        if sources.is_empty() {
+            return Err(PlaythroughError::Parse {
+                line: 1,
+                message: "There must be at least one source".to_owned(),
+            });
+        }
+        Ok(Self { sources, steps })
+    }
+
+    /// Start the game on a headless backend, with saves only kept in memory, and do each step.
+    pub fn run(&self) -> Report {
+        let game = match Headless::from_files(&self.sources) {
+            Ok(game) => game,
+            Err(err) => {
+                return Report {
+                    started: Err(err.to_string()),
+                    steps: Vec::new(),
+                    skipped: self.steps.len(),
+                }
+            }
+        };
+
+        let mut results = Vec::with_capacity(self.steps.len());
+        for (index, step) in self.steps.iter().enumerate() {
+            let result = step.kind.run(&game);
+            let stop = result.is_err() && !matches!(step.kind, StepKind::Expect(_));
+            results.push(StepResult {
+                line: step.line,
+                text: step.text.clone(),
+                result,
+            });
+            if stop {
+                return Report {
+                    started: Ok(()),
+                    steps: results,
+                    skipped: self.steps.len() - index - 1,
+                };
+            }
+        }
+        // Throw away anything the scripts printed, so that it doesn't end up in the next game
+        game.output();
+        Report {
+            started: Ok(()),
+            steps: results,
+            skipped: 0,
+        }
+    }
+}
+
+fn parse_expectation(text: &str) -> Result<Expectation, &'static str> {
+    let (kind, argument) = match text.find(' ') {
+        Some(index) => (&text[..index], text[index + 1..].trim()),
+        None => (text, ""),
+    };
+    if let Some(path) = kind.strip_prefix("info.") {
+        // Allow writing it without spaces, like `info.gold==5`
+        let index = text.find("==").ok_or("Expected 'info.<path> == <value>'")?;
+        let path = &path[..path.find("==").unwrap_or_else(|| path.len())];
+        let value = text[index + 2..].trim();
+        if path.is_empty() || value.is_empty() {
+            return Err("Expected 'info.<path> == <value>'");
+        }
+        return Ok(Expectation::Info {
+            path: path.split('.').map(str::to_owned).collect(),
+            value: value.to_owned(),
+        });
+    }
+    if argument.is_empty() {
+        return Err("Expected something to compare against");
+    }
+    let argument = argument.to_owned();
+    match kind {
+        "scene" => Ok(Expectation::Scene(argument)),
+        "title" => Ok(Expectation::Title(argument)),
+        "text" => Ok(Expectation::Text(argument)),
+        "choice" => Ok(Expectation::Choice(argument)),
+        _ => Err("Unknown expectation, expected one of scene, title, text, choice or info.<path>"),
+    }
+}
+
+impl StepKind {
+    fn run(&self, game: &Headless) -> Result<(), String> {
+        match self {
+            Self::Choose(text) => game.choose_text(text).map_err(|err| err.to_string()),
+            Self::Input(text) => game.submit(text).map_err(|err| err.to_string()),
+            Self::Undo => match game.undo() {
+                Ok(true) => Ok(()),
+                Ok(false) => Err("There was nothing to undo".to_owned()),
+                Err(err) => Err(err.to_string()),
+            },
+            Self::Wait(seconds) => game
+                .advance(*seconds)
+                .map(|_| ())
+                .map_err(|err| err.to_string()),
+            Self::Expect(expectation) => expectation.check(game),
+        }
+    }
+}
+
+impl Expectation {
+    fn check(&self, game: &Headless) -> Result<(), String> {
+        match self {
+            Self::Scene(scene) => match game.scene() {
+                Some(current) if &current == scene => Ok(()),
+                Some(current) => Err(format!("The scene was '{}'", current)),
+                None => Err("No scene was entered".to_owned()),
+            },
+            Self::Title(text) => {
+                let title = game.title();
+                if title.contains(text.as_str()) {
+                    Ok(())
+                } else {
+                    Err(format!("The title was '{}'", title))
+                }
+            }
+            Self::Text(text) => {
+                let current = game.text();
+                if current.contains(text.as_str()) {
+                    Ok(())
+                } else {
+                    Err(format!("The text was '{}'", current))
+                }
+            }
+            Self::Choice(text) => match game.choices() {
+                Some(choices) if choices.contains(text) => Ok(()),
+                Some(choices) => Err(format!("The choices were '{}'", choices.join("', '"))),
+                None => Err("No choice was being asked".to_owned()),
+            },
+            Self::Info { path, value } => {
+                let info = game.info().map_err(|err| err.to_string())?;
+                let name = format!("info.{}", path.join("."));
+                match lookup(&info, path) {
+                    Some(current) if &current.to_string() == value => Ok(()),
+                    Some(current) => Err(format!("{} was {}", name, current)),
+                    None => Err(format!("{} doesn't exist", name)),
+                }
+            }
+        }
+    }
+}
+
+fn lookup<'a>(info: &'a BTreeMap<String, SaveValue>, path: &[String]) -> Option<&'a SaveValue> {
+    let (first, rest) = path.split_first()?;
+    let mut value = info.get(first)?;
+    for key in rest {
+        value = match value {
+            SaveValue::Object(object)
+            | SaveValue::Struct(_, object)
+            | SaveValue::StructVariant(_, object) => object.get(key)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepResult {
+    pub line: usize,
+    pub text: String,
+    /// Why the step failed
+    pub result: Result<(), String>,
+}
+
+/// How each step of a playthrough went.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// Whether the game started, which is needed for any step to run
+    pub started: Result<(), String>,
+    pub steps: Vec<StepResult>,
+    /// How many steps weren't run, because a step before them couldn't be done
+    pub skipped: usize,
+}
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.started.is_ok() && self.skipped == 0 && self.steps.iter().all(|s| s.result.is_ok())
+    }
+}
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Err(err) = &self.started {
+            writeln!(f, "  FAIL   starting the game")?;
+            writeln!(f, "         {}", err)?;
+        }
+        for step in self.steps.iter() {
+            match &step.result {
+                Ok(()) => writeln!(f, "  ok     {}: {}", step.line, step.text)?,
+                Err(err) => {
+                    writeln!(f, "  FAIL   {}: {}", step.line, step.text)?;
+                    writeln!(f, "         {}", err)?;
+                }
+            }
+        }
+        if self.skipped != 0 {
+            writeln!(f, "  skipped {} steps", self.skipped)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Playthrough, PlaythroughError> {
+        Playthrough::parse(text, Path::new("examples"))
+    }
+
+    fn parse_error(text: &str) -> (usize, String) {
+        match parse(text) {
+            Err(PlaythroughError::Parse { line, message }) => (line, message),
+            result => panic!("Expected a parse error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn parse_steps() {
+        let playthrough = parse(
+            "# A comment\n\
+             source demon.rune\n\
+             source market/market.rune\n\
+             \n\
+             choose Enter Tavern\n\
+             input   Bob \n\
+             input\n\
+             undo\n\
+             wait 2.5\n\
+             expect scene tavern\n\
+             expect title Cheap Tavern\n\
+             expect text hooded man\n\
+             expect choice Leave Tavern\n\
+             expect info.player.name == \"Bob\"\n\
+             expect info.gold==5\n",
+        )
+        .unwrap();
+        assert_eq!(
+            playthrough.sources,
+            vec![
+                Path::new("examples").join("demon.rune"),
+                Path::new("examples").join("market/market.rune"),
+            ]
+        );
+        let steps: Vec<_> = playthrough
+            .steps
+            .iter()
+            .map(|step| (step.line, step.kind.clone()))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (5, StepKind::Choose("Enter Tavern".to_owned())),
+                (6, StepKind::Input("Bob".to_owned())),
+                (7, StepKind::Input(String::new())),
+                (8, StepKind::Undo),
+                (9, StepKind::Wait(2.5)),
+                (
+                    10,
+                    StepKind::Expect(Expectation::Scene("tavern".to_owned()))
+                ),
+                (
+                    11,
+                    StepKind::Expect(Expectation::Title("Cheap Tavern".to_owned()))
+                ),
+                (
+                    12,
+                    StepKind::Expect(Expectation::Text("hooded man".to_owned()))
+                ),
+                (
+                    13,
+                    StepKind::Expect(Expectation::Choice("Leave Tavern".to_owned()))
+                ),
+                (
+                    14,
+                    StepKind::Expect(Expectation::Info {
+                        path: vec!["player".to_owned(), "name".to_owned()],
+                        value: "\"Bob\"".to_owned(),
+                    })
+                ),
+                (
+                    15,
+                    StepKind::Expect(Expectation::Info {
+                        path: vec!["gold".to_owned()],
+                        value: "5".to_owned(),
+                    })
+                ),
+            ]
+        );
+        assert_eq!(playthrough.steps[1].text, "input   Bob");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            parse_error("# Nothing\n"),
+            (1, "There must be at least one source".to_owned())
+        );
+        assert_eq!(
+            parse_error("source a.rune\nundo\nsource b.rune"),
+            (3, "Sources must come before any other step".to_owned())
+        );
+        assert_eq!(
+            parse_error("source a.rune\njump"),
+            (2, "Unknown step 'jump'".to_owned())
+        );
+        assert_eq!(
+            parse_error("source"),
+            (1, "Bad argument for 'source'".to_owned())
+        );
+        assert_eq!(
+            parse_error("source a.rune\nchoose"),
+            (2, "Bad argument for 'choose'".to_owned())
+        );
+        assert_eq!(
+            parse_error("source a.rune\nundo twice"),
+            (2, "Bad argument for 'undo'".to_owned())
+        );
+        for wait in &["wait", "wait soon", "wait -1"] {
+            assert_eq!(
+                parse_error(&format!("source a.rune\n{}", wait)),
+                (2, "Expected a number of seconds for 'wait'".to_owned())
+            );
+        }
+        assert_eq!(
+            parse_error("source a.rune\nexpect title"),
+            (2, "Expected something to compare against".to_owned())
+        );
+        for info in &[
+            "expect info.gold",
+            "expect info.gold ==",
+            "expect info.== 5",
+        ] {
+            assert_eq!(
+                parse_error(&format!("source a.rune\n{}", info)),
+                (2, "Expected 'info.<path> == <value>'".to_owned())
+            );
+        }
+        assert_eq!(
+            parse_error("source a.rune\nexpect smell cheap alcohol").1,
+            "Unknown expectation, expected one of scene, title, text, choice or info.<path>"
+        );
+    }
+
+    #[test]
+    fn lookup_info() {
+        let player: BTreeMap<_, _> = vec![("name".to_owned(), SaveValue::String("Bob".to_owned()))]
+            .into_iter()
+            .collect();
+        let info: BTreeMap<_, _> = vec![
+            ("gold".to_owned(), SaveValue::Integer(5)),
+            (
+                "player".to_owned(),
+                SaveValue::Struct("Player".to_owned(), player),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let path = |path: &str| path.split('.').map(str::to_owned).collect::<Vec<_>>();
+        assert_eq!(lookup(&info, &path("gold")), Some(&SaveValue::Integer(5)));
+        assert_eq!(
+            lookup(&info, &path("player.name")).map(ToString::to_string),
+            Some("\"Bob\"".to_owned())
+        );
+        assert_eq!(lookup(&info, &path("player.age")), None);
+        assert_eq!(lookup(&info, &path("gold.amount")), None);
+        assert_eq!(lookup(&info, &path("silver")), None);
+    }
+}
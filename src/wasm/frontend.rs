@@ -0,0 +1,683 @@
+use std::{
+    cell::{Cell, RefCell},
+    convert::TryFrom,
+    rc::Rc,
+};
+
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+
+use super::util::{clear_element, document, window};
+use crate::{
+    engine::{
+        bars::{BarContent, RenderedBar},
+        error::EngineError,
+    },
+    frontend::{
+        ChoiceCallback, Frontend, InputCallback, InputKind, InputValidator, Support, Timeout,
+        Validation, ERROR_TITLE,
+    },
+    markup,
+};
+
+/// An element that displays markup, rendered as HTML.
+#[derive(Debug)]
+pub struct TextElement {
+    element: web_sys::HtmlElement,
+    /// The markup being displayed, kept so that appending can re-render it as a whole.
+    markup: RefCell<String>,
+}
+impl TextElement {
+    pub fn new(element: web_sys::HtmlElement) -> Self {
+        Self {
+            element,
+            markup: RefCell::new(String::new()),
+        }
+    }
+
+    pub fn set_text(&self, text: &str) {
+        *self.markup.borrow_mut() = text.to_owned();
+        self.render();
+    }
+
+    pub fn clear_text(&self) {
+        self.set_text("");
+    }
+
+    pub fn append_text(&self, text: &str) {
+        self.markup.borrow_mut().push_str(text);
+        self.render();
+    }
+
+    fn render(&self) {
+        self.element
+            .set_inner_html(&render_markup(&self.markup.borrow()));
+    }
+}
+
+fn render_markup(text: &str) -> String {
+    markup::to_html(&markup::parse(text))
+}
+
+/// Displays the game through elements on the webpage.
+#[derive(Debug)]
+pub struct WebFrontend {
+    /// Title text
+    pub title_element: TextElement,
+    /// Display text
+    pub text_element: TextElement,
+    /// Inputs.
+    /// Text inputs, buttons, etc.
+    pub input_element: web_sys::Element,
+    /// Where bars are displayed, such as a sidebar. Bars aren't shown if there isn't one.
+    pub bar_element: Option<web_sys::Element>,
+    /// The interval counting down the timed choice being asked, if there is one
+    timer: Rc<Cell<Option<i32>>>,
+    /// The `keydown` listener for the shortcuts of the choices being asked, if there are any
+    shortcuts: Rc<RefCell<Option<js_sys::Function>>>,
+}
+impl WebFrontend {
+    pub fn new(
+        title_element: TextElement,
+        text_element: TextElement,
+        input_element: web_sys::Element,
+        bar_element: Option<web_sys::Element>,
+    ) -> Self {
+        Self {
+            title_element,
+            text_element,
+            input_element,
+            bar_element,
+            timer: Rc::new(Cell::new(None)),
+            shortcuts: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Add a button for each choice, calling `callback` with the index of the one clicked.
+    fn add_choices(
+        &self,
+        choices: Vec<String>,
+        callback: ChoiceCallback,
+    ) -> Result<(), EngineError> {
+        remove_shortcuts(&self.shortcuts);
+        // Every button needs access to the callback, but only one of them will ever get to call it.
+        let callback = Rc::new(RefCell::new(Some(callback)));
+        let mut buttons = Vec::with_capacity(choices.len());
+        // We use the index as a way of referring to the specific button that was pressed so
+        // that the callback can look up the data itself.
+        for (index, text) in choices.iter().enumerate() {
+            let display_button = {
+                let input_element = self.input_element.clone();
+                let shortcuts = self.shortcuts.clone();
+                let callback = callback.clone();
+                let shortcut = if index < MAX_SHORTCUTS {
+                    Some(index + 1)
+                } else {
+                    None
+                };
+                DisplayButton::new(
+                    text,
+                    shortcut,
+                    Closure::once(move |_event| {
+                        // Specifically remove elements before calling anything.
+                        // _Do not_ do this after calling, as that breaks things.
+                        clear_element(input_element);
+                        remove_shortcuts(&shortcuts);
+                        let callback = callback.borrow_mut().take();
+                        if let Some(callback) = callback {
+                            // Errors have already been shown, so they only need logging.
+                            if let Err(err) = callback(index) {
+                                crate::log_info(&err.to_string());
+                            }
+                        }
+                    }),
+                )
+            }
+            .map_err(|err| frontend_error("Failed to create display button", err))?;
+            display_button
+                .add_to(&self.input_element)
+                .map_err(|err| frontend_error("Failed to add display button to inputs", err))?;
+            buttons.push(display_button.button);
+        }
+        self.add_shortcuts(buttons)
+    }
+
+    /// Let the keyboard pick between the buttons. The number keys click the button with that
+    /// number, and the arrow keys move focus between them, so that Enter clicks the focused one
+    /// as it does for any button. Tab moves between them as usual.
+    fn add_shortcuts(&self, buttons: Vec<web_sys::HtmlButtonElement>) -> Result<(), EngineError> {
+        if buttons.is_empty() {
+            return Ok(());
+        }
+        let closure = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
+            if event.ctrl_key() || event.alt_key() || event.meta_key() || event.default_prevented()
+            {
+                return;
+            }
+            // Typing into a field elsewhere on the page shouldn't pick a choice
+            let typing = event
+                .target()
+                .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+                .map_or(false, |target| {
+                    matches!(target.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT")
+                });
+            if typing {
+                return;
+            }
+
+            let focused = document().active_element().and_then(|element| {
+                buttons
+                    .iter()
+                    .position(|button| button.is_same_node(Some(element.as_ref())))
+            });
+            let count = buttons.len();
+            let key = event.key();
+            let move_to = match key.as_str() {
+                "ArrowDown" | "ArrowRight" => Some(focused.map_or(0, |index| (index + 1) % count)),
+                "ArrowUp" | "ArrowLeft" => {
+                    Some(focused.map_or(count - 1, |index| (index + count - 1) % count))
+                }
+                _ => None,
+            };
+            if let Some(index) = move_to {
+                event.prevent_default();
+                let _ = buttons[index].focus();
+                return;
+            }
+
+            let button = key
+                .parse::<usize>()
+                .ok()
+                .filter(|number| (1..=MAX_SHORTCUTS).contains(number))
+                .and_then(|number| buttons.get(number - 1));
+            if let Some(button) = button {
+                event.prevent_default();
+                // This removes the listener, which is fine as only our handle to it is dropped
+                button.click();
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        // May leak memory. See Comment above `DisplayButton` about a specific rust wasm problem
+        let function: js_sys::Function = closure.into_js_value().unchecked_into();
+        document()
+            .add_event_listener_with_callback("keydown", &function)
+            .map_err(|err| frontend_error("Failed to listen for choice shortcuts", err))?;
+        *self.shortcuts.borrow_mut() = Some(function);
+        Ok(())
+    }
+}
+
+/// An error from the browser in displaying something, with what was being done at the time.
+fn frontend_error(context: &str, error: JsValue) -> EngineError {
+    let error = error
+        .as_string()
+        .or_else(|| {
+            error
+                .dyn_ref::<js_sys::Error>()
+                .map(|error| String::from(error.message()))
+        })
+        .unwrap_or_else(|| format!("{:?}", error));
+    EngineError::Frontend(format!("{}: {}", context, error))
+}
+
+/// How many choices have a number key as a shortcut.
+const MAX_SHORTCUTS: usize = 9;
+
+/// Stop listening for the shortcuts of the choices, once they are no longer being asked, so that
+/// they don't pick a choice in the next scene.
+fn remove_shortcuts(shortcuts: &RefCell<Option<js_sys::Function>>) {
+    let function = shortcuts.borrow_mut().take();
+    if let Some(function) = function {
+        let _ = document().remove_event_listener_with_callback("keydown", &function);
+    }
+}
+
+/// How often the countdown of a timed choice is updated, in milliseconds.
+const COUNTDOWN_INTERVAL: i32 = 100;
+
+/// Stop counting down the timed choice, if one was being asked, so that it doesn't time out.
+fn stop_timer(timer: &Cell<Option<i32>>) {
+    if let Some(handle) = timer.take() {
+        window().clear_interval_with_handle(handle);
+    }
+}
+impl Frontend for WebFrontend {
+    fn support(&self) -> Support {
+        Support { image: true }
+    }
+
+    fn set_title(&self, text: &str) {
+        self.title_element.set_text(text);
+    }
+    fn append_title(&self, text: &str) {
+        self.title_element.append_text(text);
+    }
+    fn clear_title(&self) {
+        self.title_element.clear_text();
+    }
+
+    fn set_text(&self, text: &str) {
+        self.text_element.set_text(text);
+    }
+    fn append_text(&self, text: &str) {
+        self.text_element.append_text(text);
+    }
+    fn clear_text(&self) {
+        self.text_element.clear_text();
+    }
+
+    fn ask_choice(
+        &self,
+        choices: Vec<String>,
+        callback: ChoiceCallback,
+    ) -> Result<(), EngineError> {
+        // A scene entered again, such as after a reload, replaces a timed choice
+        stop_timer(&self.timer);
+        self.add_choices(choices, callback)
+    }
+
+    fn ask_choice_timed(
+        &self,
+        choices: Vec<String>,
+        timeout: Timeout,
+        callback: ChoiceCallback,
+    ) -> Result<(), EngineError> {
+        stop_timer(&self.timer);
+        // Picking a choice stops the countdown
+        let timer = self.timer.clone();
+        self.add_choices(
+            choices,
+            Box::new(move |index| {
+                stop_timer(&timer);
+                callback(index)
+            }),
+        )?;
+
+        // The countdown is shown after the buttons, with the seconds left
+        let countdown = document()
+            .create_element("div")
+            .map_err(|err| frontend_error("Failed to create countdown", err))?;
+        countdown.set_class_name("aleph-countdown");
+        let deadline = js_sys::Date::now() + timeout.seconds * 1000.0;
+        let show_countdown = move || {
+            let left = ((deadline - js_sys::Date::now()) / 1000.0).max(0.0);
+            countdown.set_text_content(Some(&format!("{}", left.ceil())));
+            left
+        };
+        show_countdown();
+        let nodes = js_sys::Array::new();
+        nodes.push(&JsValue::from(countdown.clone()));
+        self.input_element
+            .append_with_node(&nodes)
+            .map_err(|err| frontend_error("Failed to add countdown to inputs", err))?;
+
+        let input_element = self.input_element.clone();
+        let timer = self.timer.clone();
+        let shortcuts = self.shortcuts.clone();
+        let mut timeout_callback = Some(timeout.callback);
+        let closure = Closure::wrap(Box::new(move || {
+            if show_countdown() > 0.0 {
+                return;
+            }
+            stop_timer(&timer);
+            clear_element(input_element.clone());
+            remove_shortcuts(&shortcuts);
+            if let Some(callback) = timeout_callback.take() {
+                if let Err(err) = callback() {
+                    crate::log_info(&err.to_string());
+                }
+            }
+        }) as Box<dyn FnMut()>);
+        let handle = window()
+            .set_interval_with_callback_and_timeout_and_arguments_0(
+                closure.as_ref().unchecked_ref(),
+                COUNTDOWN_INTERVAL,
+            )
+            .map_err(|err| frontend_error("Failed to start countdown", err))?;
+        // May leak memory. See Comment above `DisplayButton` about a specific rust wasm problem
+        closure.forget();
+        self.timer.set(Some(handle));
+        Ok(())
+    }
+
+    fn ask_input(
+        &self,
+        default_text: &str,
+        kind: InputKind,
+        validator: InputValidator,
+        live: bool,
+        callback: InputCallback,
+    ) -> Result<(), EngineError> {
+        stop_timer(&self.timer);
+        remove_shortcuts(&self.shortcuts);
+        let input_element = self.input_element.clone();
+        // The keyup listener may fire many times, but we only allow submitting once.
+        let callback = RefCell::new(Some(callback));
+        let text_input_element =
+            TextInput::new(default_text, &kind, validator, live, move |text: String| {
+                // Remove the input element, because we only allow submitting it once.
+                clear_element(input_element.clone());
+                let callback = callback.borrow_mut().take();
+                if let Some(callback) = callback {
+                    if let Err(err) = callback(text) {
+                        crate::log_info(&err.to_string());
+                    }
+                }
+            })
+            .map_err(|err| frontend_error("Failed to create text input", err))?;
+
+        text_input_element
+            .add_to(&self.input_element)
+            .map_err(|err| frontend_error("Failed to add text input to inputs", err))
+    }
+
+    fn set_bars(&self, bars: &[RenderedBar]) {
+        let bar_element = match &self.bar_element {
+            Some(bar_element) => bar_element,
+            None => return,
+        };
+        // Everything is either escaped or rendered from markup, so it is safe to use as html
+        let mut html = String::new();
+        for bar in bars {
+            html.push_str("<div class=\"aleph-bar\"><h4>");
+            html.push_str(&render_markup(&markup::escape(&bar.name)));
+            html.push_str("</h4>");
+            match &bar.content {
+                BarContent::Text(text) => html.push_str(&render_markup(text)),
+                BarContent::Rows(rows) => {
+                    html.push_str("<table>");
+                    for (label, value) in rows {
+                        html.push_str(&format!(
+                            "<tr><td>{}</td><td>{}</td></tr>",
+                            render_markup(label),
+                            render_markup(value)
+                        ));
+                    }
+                    html.push_str("</table>");
+                }
+            }
+            html.push_str("</div>");
+        }
+        bar_element.set_inner_html(&html);
+    }
+
+    fn show_error(&self, error: &EngineError) {
+        stop_timer(&self.timer);
+        remove_shortcuts(&self.shortcuts);
+        clear_element(self.input_element.clone());
+        self.title_element.set_text(ERROR_TITLE);
+        self.text_element
+            .set_text(&markup::escape(&error.to_string()));
+    }
+}
+
+// Closure notes related to potential leaking memory:
+// So, it appears that Rust-Wasm closures are kindof irritating (beyond their verbose syntax)
+// When you create a closure:
+// ```rust
+// let closure = Closure::new(Box::new(|a: i32| -> i32 { a+1 }) as Box<dyn Fn(i32) -> i32>);
+// ```
+// Then you want to put it on something, like an event listener:
+// `element.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())?;`
+// Now, the documentation uses `closure.forget();`, I'd even say encourages it.
+// This just leaks the memory for the closure, letting it live forever... which is bad.
+// So, we want to keep the closure alive for as long as its needed by the js engine.
+// My first idea was to wrap it in an (A)Rc, but that wouldn't work as you no longer have
+// an alive strong Arc reference to the closure once it is converted to the &Function.
+// Next, after delving through some issues in the github, I saw the recommended method as
+// storing them somewhere so they aren't dropped. Okay, that's sensible.. but where do I
+// store them? While it might be possible to set them up as individually allocated bits
+// in a vector on my main state object, that is a *pain* and complicates code excessively.
+// My closures also kindof need to destroy the element holding them, so they have to mess
+// with the state when their called. I think this might work fine, but it sounds like it could
+// easly lead to long debugging sessions as it tries to destroy the struct containing itself while
+// in itself.
+// So, essentially have to wait for weakrefs to be supported in all browsers to properly get basic
+// garbage collection. So, until then it will likely leak memory.
+// An FnOnce - but I believe only if called! - will be collected properly though.
+
+pub struct DisplayButton {
+    pub button: web_sys::HtmlButtonElement,
+}
+impl DisplayButton {
+    /// Construct a new display button.
+    /// The text is shown after a hint for the number key that is its shortcut, if it has one.
+    pub fn new(
+        text: &str,
+        shortcut: Option<usize>,
+        callback: Closure<dyn FnMut(web_sys::MouseEvent)>,
+    ) -> Result<Self, JsValue> {
+        let button = document()
+            .create_element("button")?
+            .dyn_into::<web_sys::HtmlButtonElement>()?;
+        match shortcut {
+            Some(shortcut) => {
+                button.set_inner_text(&format!(" {}", text));
+                let hint = document().create_element("kbd")?;
+                hint.set_class_name("aleph-shortcut");
+                hint.set_text_content(Some(&shortcut.to_string()));
+                button.prepend_with_node_1(&hint)?;
+                button.set_attribute("aria-keyshortcuts", &shortcut.to_string())?;
+            }
+            None => button.set_inner_text(text),
+        }
+
+        // listen to click events
+        button.add_event_listener_with_callback("click", callback.as_ref().unchecked_ref())?;
+        callback.forget();
+
+        Ok(Self { button })
+    }
+
+    pub fn add_to(&self, element: &web_sys::Element) -> Result<(), JsValue> {
+        // TODO: better methd for adding it than this.
+        let nodes = js_sys::Array::new();
+        nodes.push(&JsValue::from(self.button.clone()));
+        element.append_with_node(&nodes)
+    }
+}
+
+const ENTER_KEYCODE: u32 = 13;
+
+/// The element that text is entered into, which depends on the kind of input.
+#[derive(Clone)]
+enum Field {
+    Input(web_sys::HtmlInputElement),
+    TextArea(web_sys::HtmlTextAreaElement),
+    /// With the text of each option, which is read back by index, as an option's `value` is its
+    /// text with the whitespace collapsed.
+    Select(web_sys::HtmlSelectElement, Vec<String>),
+}
+impl Field {
+    fn new(kind: &InputKind, text: &str) -> Result<Self, JsValue> {
+        let field = match kind {
+            InputKind::Multiline => {
+                let textarea = document()
+                    .create_element("textarea")?
+                    .dyn_into::<web_sys::HtmlTextAreaElement>()?;
+                textarea.set_default_value(text);
+                Self::TextArea(textarea)
+            }
+            InputKind::Select(options) => {
+                let select = document()
+                    .create_element("select")?
+                    .dyn_into::<web_sys::HtmlSelectElement>()?;
+                for (index, option) in options.iter().enumerate() {
+                    let element = document().create_element("option")?;
+                    element.set_attribute("value", &index.to_string())?;
+                    element.set_text_content(Some(option));
+                    if option == text {
+                        element.set_attribute("selected", "")?;
+                    }
+                    select.append_child(&element)?;
+                }
+                Self::Select(select, options.clone())
+            }
+            _ => {
+                let input = document()
+                    .create_element("input")?
+                    .dyn_into::<web_sys::HtmlInputElement>()?;
+                match kind {
+                    InputKind::Secret => input.set_type("password"),
+                    InputKind::Number { min, max, integer } => {
+                        input.set_type("number");
+                        input.set_min(&min.to_string());
+                        input.set_max(&max.to_string());
+                        input.set_step(if *integer { "1" } else { "any" });
+                    }
+                    _ => {}
+                }
+                input.set_default_value(text);
+                Self::Input(input)
+            }
+        };
+        Ok(field)
+    }
+
+    fn value(&self) -> String {
+        match self {
+            Self::Input(input) => input.value(),
+            Self::TextArea(textarea) => textarea.value(),
+            // Nothing picked is -1, which isn't an option and so is rejected
+            Self::Select(select, options) => usize::try_from(select.selected_index())
+                .ok()
+                .and_then(|index| options.get(index).cloned())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn element(&self) -> &web_sys::HtmlElement {
+        match self {
+            Self::Input(input) => input,
+            Self::TextArea(textarea) => textarea,
+            Self::Select(select, _) => select,
+        }
+    }
+}
+
+/// A field to enter text into, with a message after it for why the text was rejected, if it was.
+/// Multi-line text is submitted with Ctrl+Enter, or with the button after it. Dropdowns also get
+/// the button, as picking an option with the mouse doesn't give a key to submit with.
+pub struct TextInput {
+    field: Field,
+    /// Holds the validator's message, styled by `.aleph-input-error`.
+    pub error: web_sys::HtmlElement,
+    /// Submits the text, for fields where Enter doesn't.
+    pub button: Option<web_sys::HtmlButtonElement>,
+}
+impl TextInput {
+    /// If `live`, then the text is validated whenever it changes, and not just when submitted.
+    pub fn new<G, F>(
+        text: &str,
+        kind: &InputKind,
+        validator_callback: G,
+        live: bool,
+        enter_callback: F,
+    ) -> Result<Self, JsValue>
+    where
+        G: 'static + Fn(String) -> Result<Validation, EngineError>,
+        F: 'static + Fn(String),
+    {
+        let field = Field::new(kind, text)?;
+
+        let error = document()
+            .create_element("span")?
+            .dyn_into::<web_sys::HtmlElement>()?;
+        error.set_class_name("aleph-input-error");
+        // So that screen readers announce the message when it changes
+        error.set_attribute("aria-live", "polite")?;
+
+        // Validate the current text, showing the message if it was rejected.
+        // `None` if the validator failed, in which case the error screen has replaced the input.
+        let validate = {
+            let field = field.clone();
+            let error = error.clone();
+            Rc::new(move || -> Option<String> {
+                let text = field.value();
+                match validator_callback(text.clone()) {
+                    Ok(Ok(())) => {
+                        error.set_inner_text("");
+                        field.element().remove_attribute("aria-invalid").ok()?;
+                        Some(text)
+                    }
+                    Ok(Err(message)) => {
+                        error.set_inner_text(&message);
+                        field.element().set_attribute("aria-invalid", "true").ok()?;
+                        None
+                    }
+                    Err(err) => {
+                        crate::log_info(&err.to_string());
+                        None
+                    }
+                }
+            })
+        };
+        let submit = {
+            let validate = validate.clone();
+            Rc::new(move || {
+                if let Some(text) = validate() {
+                    enter_callback(text);
+                }
+            })
+        };
+
+        let multiline = *kind == InputKind::Multiline;
+        let enter_submit = submit.clone();
+        let closure = Closure::wrap(Box::new(move |event: web_sys::KeyboardEvent| {
+            // Enter, but not shift-enter. A new line needs Ctrl+Enter (or Cmd+Enter) to submit.
+            let modified = event.ctrl_key() || event.meta_key();
+            if event.key_code() == ENTER_KEYCODE && !event.shift_key() && (modified || !multiline) {
+                enter_submit();
+            }
+        }) as Box<dyn Fn(_)>);
+
+        // May leak memory. See Comment above this structure about a specific rust wasm problem
+        let closure = closure.into_js_value();
+
+        // SAFETY/SOUNDNESS: We *know* that the closure is a valid function, and so we hand it over.
+        let function_ref: &js_sys::Function = closure.unchecked_ref();
+        field
+            .element()
+            .add_event_listener_with_callback("keyup", function_ref)?;
+
+        if live {
+            let closure = Closure::wrap(Box::new(move || {
+                validate();
+            }) as Box<dyn Fn()>);
+            // May leak memory. See Comment above `DisplayButton` about a specific rust wasm problem
+            let closure = closure.into_js_value();
+            field
+                .element()
+                .add_event_listener_with_callback("input", closure.unchecked_ref())?;
+        }
+
+        let button = if multiline || matches!(kind, InputKind::Select(_)) {
+            let button = document()
+                .create_element("button")?
+                .dyn_into::<web_sys::HtmlButtonElement>()?;
+            button.set_inner_text("Submit");
+            let closure = Closure::wrap(Box::new(move || submit()) as Box<dyn Fn()>);
+            // May leak memory. See Comment above `DisplayButton` about a specific rust wasm problem
+            let closure = closure.into_js_value();
+            button.add_event_listener_with_callback("click", closure.unchecked_ref())?;
+            Some(button)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            field,
+            error,
+            button,
+        })
+    }
+
+    pub fn add_to(&self, element: &web_sys::Element) -> Result<(), JsValue> {
+        let nodes = js_sys::Array::new();
+        nodes.push(&JsValue::from(self.field.element().clone()));
+        if let Some(button) = &self.button {
+            nodes.push(&JsValue::from(button.clone()));
+        }
+        nodes.push(&JsValue::from(self.error.clone()));
+        element.append_with_node(&nodes)
+    }
+}
@@ -1,3 +1,11 @@
+#[cfg(target_arch = "wasm32")]
+pub mod frontend;
 pub mod rune_core;
+#[cfg(target_arch = "wasm32")]
 pub mod rune_lib;
+#[cfg(target_arch = "wasm32")]
+pub mod storage;
+#[cfg(target_arch = "wasm32")]
+pub mod user_info;
+#[cfg(target_arch = "wasm32")]
 pub mod util;
@@ -1,59 +1,114 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    io::Write,
+    rc::Rc,
+};
+
 use runestick::{ContextError, Module};
 
 // Most of this is slightly modified from the rune wasm example
 
 /// Overwrite existing standard functions to perform correctly in wasm.
-pub fn create_module() -> Result<Module, ContextError> {
+/// What scripts print goes to `output`, so that games running alongside each other don't take
+/// each other's output.
+pub fn create_module(output: &Output) -> Result<Module, ContextError> {
     let mut module = Module::new(&["std"]);
-    module.function(&["print"], rune_print)?;
-    module.function(&["println"], rune_println)?;
-    module.raw_fn(&["dbg"], rune_dbg)?;
+    // Only the id is held onto, as the functions can't hold onto anything that isn't `Send`
+    let id = output.id();
+    module.function(&["print"], move |message: &str| rune_print(id, message))?;
+    module.function(&["println"], move |message: &str| rune_println(id, message))?;
+    module.raw_fn(&["dbg"], move |stack, args| rune_dbg(id, stack, args))?;
     Ok(module)
 }
 
 thread_local! {
-    static OUT: std::cell::RefCell<std::io::Cursor<Vec<u8>>> = std::cell::RefCell::new(std::io::Cursor::new(Vec::new()));
+    /// What the scripts of each game have printed, by the id of the game's `Output`.
+    static OUT: RefCell<HashMap<usize, Vec<u8>>> = RefCell::new(HashMap::new());
+    static NEXT_ID: Cell<usize> = Cell::new(0);
 }
 
-/// Drain the output stored in the thread local `OUT`.
-/// If non-utf8 output, then it will return None.
-pub fn drain_output() -> Option<String> {
-    OUT.with(|out| {
-        let mut out = out.borrow_mut();
-        let out = std::mem::take(&mut *out).into_inner();
-        String::from_utf8(out).ok()
-    })
+/// Where a game's scripts print to, until the host takes it with `drain`.
+/// Clones share the same output, which is forgotten once every clone is dropped.
+#[derive(Debug, Clone)]
+pub struct Output {
+    id: Rc<OutputId>,
 }
+impl Default for Output {
+    fn default() -> Self {
+        let id = NEXT_ID.with(|next| {
+            let id = next.get();
+            next.set(id + 1);
+            id
+        });
+        OUT.with(|out| out.borrow_mut().insert(id, Vec::new()));
+        Self {
+            id: Rc::new(OutputId(id)),
+        }
+    }
+}
+impl Output {
+    fn id(&self) -> usize {
+        self.id.0
+    }
 
-fn rune_print(message: &str) -> Result<(), runestick::Panic> {
-    use std::io::Write;
+    /// Take everything that was printed since the last time it was drained.
+    /// If non-utf8 output, then it will return None.
+    pub fn drain(&self) -> Option<String> {
+        let out = with_output(self.id(), |out| Ok(std::mem::take(out))).ok()?;
+        String::from_utf8(out).ok()
+    }
 
-    OUT.with(|out| {
-        let mut out = out.borrow_mut();
-        write!(out, "{}", message).map_err(runestick::Panic::custom)
-    })
+    /// Write a line to the output, as if a script had printed it.
+    /// Used for reporting things that shouldn't interrupt the game.
+    pub fn write_line(&self, message: &str) {
+        // Writing to an in-memory buffer can't fail
+        let _ = rune_println(self.id(), message);
+    }
 }
 
-fn rune_println(message: &str) -> Result<(), runestick::Panic> {
-    use std::io::Write;
+#[derive(Debug)]
+struct OutputId(usize);
+impl Drop for OutputId {
+    fn drop(&mut self) {
+        // The thread may be shutting down, in which case there is nothing left to forget
+        let _ = OUT.try_with(|out| out.borrow_mut().remove(&self.0));
+    }
+}
 
-    OUT.with(|out| {
-        let mut out = out.borrow_mut();
-        writeln!(out, "{}", message).map_err(runestick::Panic::custom)
+/// Run `f` on the output with the id, which is an error if it has already been dropped.
+fn with_output<T>(
+    id: usize,
+    f: impl FnOnce(&mut Vec<u8>) -> std::io::Result<T>,
+) -> Result<T, runestick::Panic> {
+    OUT.with(|out| match out.borrow_mut().get_mut(&id) {
+        Some(out) => f(out).map_err(runestick::Panic::custom),
+        None => Err(runestick::Panic::custom("The game's output is gone")),
     })
 }
 
-fn rune_dbg(stack: &mut runestick::Stack, args: usize) -> Result<(), runestick::VmError> {
-    use std::io::Write;
+fn rune_print(id: usize, message: &str) -> Result<(), runestick::Panic> {
+    with_output(id, |out| write!(out, "{}", message))
+}
 
-    OUT.with(|out| {
-        let mut out = out.borrow_mut();
+fn rune_println(id: usize, message: &str) -> Result<(), runestick::Panic> {
+    with_output(id, |out| writeln!(out, "{}", message))
+}
 
-        for value in stack.drain_stack_top(args)? {
-            writeln!(out, "{:?}", value).map_err(runestick::VmError::panic)?;
+fn rune_dbg(
+    id: usize,
+    stack: &mut runestick::Stack,
+    args: usize,
+) -> Result<(), runestick::VmError> {
+    let values = stack.drain_stack_top(args)?;
+    with_output(id, |out| {
+        for value in values {
+            writeln!(out, "{:?}", value)?;
         }
-
-        stack.push(runestick::Value::Unit);
         Ok(())
     })
+    .map_err(runestick::VmError::panic)?;
+
+    stack.push(runestick::Value::Unit);
+    Ok(())
 }
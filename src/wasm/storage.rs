@@ -0,0 +1,75 @@
+use crate::engine::{
+    save::SaveError,
+    storage::{check_slot, SaveStorage},
+};
+
+use super::util::window;
+
+fn storage_error(error: wasm_bindgen::JsValue) -> SaveError {
+    SaveError::Storage(
+        error
+            .as_string()
+            .unwrap_or_else(|| "localStorage error".to_owned()),
+    )
+}
+
+/// Keeps saves in the browser's `localStorage`.
+/// Each key is prefixed so that multiple games on the same site don't overwrite each other.
+#[derive(Debug)]
+pub struct LocalStorage {
+    prefix: String,
+}
+impl LocalStorage {
+    pub const DEFAULT_PREFIX: &'static str = "aleph-naught";
+
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: format!("{}:save:", prefix),
+        }
+    }
+
+    fn storage(&self) -> Result<web_sys::Storage, SaveError> {
+        window()
+            .local_storage()
+            .map_err(storage_error)?
+            .ok_or_else(|| SaveError::Storage("localStorage is not available".to_owned()))
+    }
+
+    fn key(&self, slot: &str) -> Result<String, SaveError> {
+        check_slot(slot)?;
+        Ok(format!("{}{}", self.prefix, slot))
+    }
+}
+impl SaveStorage for LocalStorage {
+    fn list(&self) -> Result<Vec<String>, SaveError> {
+        let storage = self.storage()?;
+        let mut slots = Vec::new();
+        for index in 0..storage.length().map_err(storage_error)? {
+            if let Some(key) = storage.key(index).map_err(storage_error)? {
+                if let Some(slot) = key.strip_prefix(&self.prefix) {
+                    slots.push(slot.to_owned());
+                }
+            }
+        }
+        slots.sort();
+        Ok(slots)
+    }
+
+    fn read(&self, slot: &str) -> Result<Option<String>, SaveError> {
+        self.storage()?
+            .get_item(&self.key(slot)?)
+            .map_err(storage_error)
+    }
+
+    fn write(&self, slot: &str, data: &str) -> Result<(), SaveError> {
+        self.storage()?
+            .set_item(&self.key(slot)?, data)
+            .map_err(storage_error)
+    }
+
+    fn delete(&self, slot: &str) -> Result<(), SaveError> {
+        self.storage()?
+            .remove_item(&self.key(slot)?)
+            .map_err(storage_error)
+    }
+}
@@ -0,0 +1,426 @@
+use runestick::Any;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+use super::{
+    frontend::{TextElement, WebFrontend},
+    storage::LocalStorage,
+    util::GetObjectPropertyError,
+};
+use crate::{
+    engine::{error::EngineError, locale::StringTable},
+    RunOptions,
+};
+
+/// Information passed in by the user when creating the instance.
+/// Its definition obviously depends on where this is being hosted.
+#[derive(Debug, Any)]
+pub struct UserInfo {
+    /// The elements that the game is displayed in
+    pub frontend: WebFrontend,
+    /// Where save slots are kept
+    pub storage: LocalStorage,
+    /// The sources and information about them
+    pub sources: SourceUserInfo,
+    pub options: RunOptions,
+}
+impl UserInfo {
+    const TITLE_ELEMENT_KEY: &'static str = "title_element";
+    const TEXT_ELEMENT_KEY: &'static str = "text_element";
+    const INPUT_ELEMENT_KEY: &'static str = "input_element";
+    // Optional: element that bars are displayed in
+    const BAR_ELEMENT_KEY: &'static str = "bar_element";
+    // Optional: prefix of the localStorage keys saves are kept under
+    const SAVE_PREFIX_KEY: &'static str = "save_prefix";
+    // Optional: whether to refuse to start when there are warnings
+    const WARNINGS_AS_ERRORS_KEY: &'static str = "warnings_as_errors";
+    // Optional: string tables, `Iterator<Item={language: String, file: String}>`, or with `code`
+    // in place of `file` for an inline table
+    const LOCALES_KEY: &'static str = "locales";
+    const LOCALE_LANGUAGE_KEY: &'static str = "language";
+    const LOCALE_FILE_KEY: &'static str = "file";
+    const LOCALE_CODE_KEY: &'static str = "code";
+    // Optional: the language to start in, which defaults to that of the first string table
+    const LANGUAGE_KEY: &'static str = "language";
+
+    pub async fn from_js_object(info: JsValue) -> Result<Self, EngineError> {
+        if !info.is_object() {
+            return Err(EngineError::Config(
+                "Failure to create startup information. Info was not an object".to_owned(),
+            ));
+        }
+
+        let title_element = super::util::get_object_property::<web_sys::HtmlElement>(
+            &info,
+            Self::TITLE_ELEMENT_KEY,
+        )?;
+        let title_element = TextElement::new(title_element);
+
+        let text_element = super::util::get_object_property::<web_sys::HtmlElement>(
+            &info,
+            Self::TEXT_ELEMENT_KEY,
+        )?;
+        let text_element = TextElement::new(text_element);
+
+        let input_element =
+            super::util::get_object_property::<web_sys::Element>(&info, Self::INPUT_ELEMENT_KEY)?;
+
+        let bar_element =
+            super::util::get_object_property::<web_sys::Element>(&info, Self::BAR_ELEMENT_KEY).ok();
+
+        let save_prefix = js_sys::Reflect::get(&info, &JsValue::from_str(Self::SAVE_PREFIX_KEY))
+            .ok()
+            .and_then(|prefix| prefix.as_string())
+            .unwrap_or_else(|| LocalStorage::DEFAULT_PREFIX.to_owned());
+
+        let warnings_as_errors =
+            js_sys::Reflect::get(&info, &JsValue::from_str(Self::WARNINGS_AS_ERRORS_KEY))
+                .ok()
+                .and_then(|value| value.as_bool())
+                .unwrap_or(false);
+
+        let language = js_sys::Reflect::get(&info, &JsValue::from_str(Self::LANGUAGE_KEY))
+            .ok()
+            .and_then(|language| language.as_string());
+
+        let source_user_info = SourceUserInfo::try_from_js_value(&info).await?;
+        let locales = Self::load_locales(&info).await?.unwrap_or_default();
+
+        Ok(Self {
+            frontend: WebFrontend::new(title_element, text_element, input_element, bar_element),
+            storage: LocalStorage::new(&save_prefix),
+            sources: source_user_info,
+            options: RunOptions {
+                warnings_as_errors,
+                locales,
+                language,
+            },
+        })
+    }
+
+    /// Get the string tables, fetching those that are given as files all at once.
+    /// `None` if `info` doesn't give any.
+    pub async fn load_locales(info: &JsValue) -> Result<Option<Vec<StringTable>>, EngineError> {
+        use js_sys::Reflect;
+
+        let locales = match Reflect::get(info, &JsValue::from_str(Self::LOCALES_KEY)) {
+            Ok(locales) if !locales.is_undefined() => locales,
+            _ => return Ok(None),
+        };
+        let locales = js_sys::try_iter(&locales)
+            .map_err(|_| SourceInfoError::IterFailure {
+                key: Self::LOCALES_KEY,
+            })?
+            .ok_or_else(|| SourceInfoError::IterFailure {
+                key: Self::LOCALES_KEY,
+            })?;
+
+        // The language of each table, and its text, or the file to fetch it from
+        let mut entries = Vec::new();
+        let mut files = Vec::new();
+        for (index, locale) in locales.enumerate() {
+            let locale: JsValue = locale.map_err(|_| SourceInfoError::ActiveIterFailure {
+                key: Self::LOCALES_KEY,
+            })?;
+            let get_string = |key: &str| {
+                Reflect::get(&locale, &JsValue::from_str(key))
+                    .ok()
+                    .and_then(|value| value.as_string())
+            };
+            let language = get_string(Self::LOCALE_LANGUAGE_KEY)
+                .ok_or(SourceInfoError::BadLocale { index })?;
+            match (
+                get_string(Self::LOCALE_FILE_KEY),
+                get_string(Self::LOCALE_CODE_KEY),
+            ) {
+                (Some(file), _) => {
+                    files.push(file.clone());
+                    entries.push((language, file, None));
+                }
+                (None, Some(code)) => entries.push((language.clone(), language, Some(code))),
+                (None, None) => return Err(SourceInfoError::BadLocale { index }.into()),
+            }
+        }
+
+        // Fetched in the same order as the files were pushed
+        let mut fetched = SourceUserInfo::fetch_all(files).await?.into_iter();
+        let mut tables = Vec::new();
+        for (language, name, code) in entries {
+            let text = match code {
+                Some(code) => code,
+                None => fetched.next().map(|(_, text)| text).unwrap_or_default(),
+            };
+            let table = StringTable::parse(&language, &text).map_err(|err| {
+                EngineError::Config(format!("In string table '{}', {}", name, err))
+            })?;
+            tables.push(table);
+        }
+        Ok(Some(tables))
+    }
+}
+
+impl From<GetObjectPropertyError> for EngineError {
+    fn from(error: GetObjectPropertyError) -> Self {
+        Self::Config(error.into())
+    }
+}
+
+pub enum SourceInfoError {
+    /// An error in getting a value that should be an iterator
+    IterFailure {
+        key: &'static str,
+    },
+    /// Experienced an error while iterating from the iterator
+    ActiveIterFailure {
+        key: &'static str,
+    },
+    ExpectedString,
+    /// An inline source was not an object with string `name` and `code` properties
+    BadInlineSource {
+        index: usize,
+    },
+    /// A string table was not an object with a string `language`, and a string `file` or `code`
+    BadLocale {
+        index: usize,
+    },
+    RequestCreationFailure {
+        file: String,
+    },
+    RequestFailure {
+        file: String,
+    },
+    /// The server responded, but not with a 2xx status
+    BadStatus {
+        file: String,
+        status: u16,
+        status_text: String,
+    },
+    BadRequestBody {
+        file: String,
+    },
+}
+impl Into<String> for SourceInfoError {
+    fn into(self) -> String {
+        match self {
+            Self::IterFailure { key } => format!("Expected property '{}' to be iterable", key),
+            Self::ActiveIterFailure { key } => format!(
+                "While iterating over '{}', experienced error in getting value",
+                key
+            ),
+            Self::ExpectedString => {
+                "Expected string, but got non-string value. (Good luck).".to_owned()
+            }
+            Self::BadInlineSource { index } => format!(
+                "Expected inline source {} to be an object like {{name: string, code: string}}",
+                index
+            ),
+            Self::BadLocale { index } => format!(
+                "Expected string table {} to be an object like {{language: string, file: string}} or {{language: string, code: string}}",
+                index
+            ),
+            Self::RequestCreationFailure { .. } => {
+                "Failed to create network request before even sending it.".to_owned()
+            }
+            Self::RequestFailure { .. } => "Failure in network request.".to_owned(),
+            // The status text is often empty, such as over HTTP/2, so the number is always given
+            Self::BadStatus {
+                file,
+                status,
+                status_text,
+            } if status_text.is_empty() => {
+                format!("Server responded to '{}' with status {}", file, status)
+            }
+            Self::BadStatus {
+                file,
+                status,
+                status_text,
+            } => format!(
+                "Server responded to '{}' with status {} '{}'",
+                file, status, status_text
+            ),
+            Self::BadRequestBody { .. } => "Failed to get request body".to_owned(),
+        }
+    }
+}
+impl From<SourceInfoError> for EngineError {
+    fn from(error: SourceInfoError) -> Self {
+        match &error {
+            SourceInfoError::RequestCreationFailure { file }
+            | SourceInfoError::RequestFailure { file }
+            | SourceInfoError::BadRequestBody { file } => Self::SourceFetch {
+                source: file.clone(),
+                status: None,
+                error: error.into(),
+            },
+            SourceInfoError::BadStatus { file, status, .. } => Self::SourceFetch {
+                source: file.clone(),
+                status: Some(*status),
+                error: error.into(),
+            },
+            _ => Self::Config(error.into()),
+        }
+    }
+}
+
+#[derive(Debug, Any)]
+pub struct SourceUserInfo {
+    pub sources: rune::Sources,
+}
+impl SourceUserInfo {
+    // Files: `Iterator<Item=String>`
+    const FILES_KEY: &'static str = "files";
+    // Sources: `Iterator<Item={name: String, code: String}>`
+    const SOURCES_KEY: &'static str = "sources";
+    const SOURCE_NAME_KEY: &'static str = "name";
+    const SOURCE_CODE_KEY: &'static str = "code";
+
+    pub async fn try_from_js_value(value: &JsValue) -> Result<Self, EngineError> {
+        use js_sys::Reflect;
+
+        let mut sources = rune::Sources::new();
+
+        // Either of files or inline sources may be left out
+        let get_optional = |key: &str| {
+            Reflect::get(value, &JsValue::from_str(key))
+                .ok()
+                .filter(|value| !value.is_undefined())
+        };
+
+        if let Some(files) = get_optional(Self::FILES_KEY) {
+            // Extract the value from the conversion into an iterator
+            // this allows us to not bother checking if it is an array of strings.
+            // all it has to be is iteratable and each iteration item is a string
+            let files = js_sys::try_iter(&files)
+                .map_err(|_| SourceInfoError::IterFailure {
+                    key: Self::FILES_KEY,
+                })?
+                .ok_or_else(|| SourceInfoError::IterFailure {
+                    key: Self::FILES_KEY,
+                })?;
+
+            let mut paths = Vec::new();
+            for file in files {
+                // Extract the value from the iterator
+                let file: JsValue = file.map_err(|_| SourceInfoError::ActiveIterFailure {
+                    key: Self::FILES_KEY,
+                })?;
+                // Get the value as a string. This is the place we should fetch the code from.
+                let file: String = file.as_string().ok_or(SourceInfoError::ExpectedString)?;
+                paths.push(file);
+            }
+
+            for (file, code) in Self::fetch_all(paths).await? {
+                sources.insert(runestick::Source::new(file, code));
+            }
+        }
+
+        // Inline code, which doesn't need fetching at all
+        if let Some(inline_sources) = get_optional(Self::SOURCES_KEY) {
+            let inline_sources = js_sys::try_iter(&inline_sources)
+                .map_err(|_| SourceInfoError::IterFailure {
+                    key: Self::SOURCES_KEY,
+                })?
+                .ok_or_else(|| SourceInfoError::IterFailure {
+                    key: Self::SOURCES_KEY,
+                })?;
+
+            for (index, source) in inline_sources.enumerate() {
+                let source: JsValue = source.map_err(|_| SourceInfoError::ActiveIterFailure {
+                    key: Self::SOURCES_KEY,
+                })?;
+                let get_string = |key: &str| {
+                    Reflect::get(&source, &JsValue::from_str(key))
+                        .ok()
+                        .and_then(|value| value.as_string())
+                        .ok_or(SourceInfoError::BadInlineSource { index })
+                };
+                let name = get_string(Self::SOURCE_NAME_KEY)?;
+                let code = get_string(Self::SOURCE_CODE_KEY)?;
+                sources.insert(runestick::Source::new(name, code));
+            }
+        }
+
+        Ok(SourceUserInfo { sources })
+    }
+
+    /// Fetch the text of every file, starting every request before waiting on any of them, so
+    /// that they are all in flight at once rather than one after another.
+    /// Every file is waited on, even after one fails, so that each failure is returned.
+    pub async fn fetch_all(files: Vec<String>) -> Result<Vec<(String, String)>, EngineError> {
+        use super::util::window;
+        use web_sys::{Request, RequestInit, RequestMode};
+
+        // Create the request options outside of the loop as it is the same very time.
+        let mut request_options = RequestInit::new();
+        request_options.method("GET");
+        request_options.mode(RequestMode::Cors);
+        let mut responses = Vec::new();
+        let mut failures = Vec::new();
+        for file in files {
+            // Create the network Request object
+            match Request::new_with_str_and_init(&file, &request_options) {
+                Ok(request) => {
+                    // Fetch using the request, and turn it into a JsFuture so that it can be
+                    // awaited
+                    let response = JsFuture::from(window().fetch_with_request(&request));
+                    responses.push((file, response));
+                }
+                Err(_) => {
+                    failures.push(EngineError::from(SourceInfoError::RequestCreationFailure {
+                        file,
+                    }))
+                }
+            }
+        }
+
+        let mut texts = Vec::new();
+        for (file, response) in responses {
+            match Self::read_response(&file, response).await {
+                Ok(text) => texts.push((file, text)),
+                Err(err) => failures.push(EngineError::from(err)),
+            }
+        }
+        for failure in failures.iter() {
+            crate::log_info(&failure.to_string());
+        }
+        match failures.len() {
+            0 => Ok(texts),
+            1 => Err(failures.remove(0)),
+            _ => Err(EngineError::SourceFetches(failures)),
+        }
+    }
+
+    /// Wait on the response to fetching the file, and get the code out of it.
+    async fn read_response(file: &str, response: JsFuture) -> Result<String, SourceInfoError> {
+        use web_sys::Response;
+
+        let response = response
+            .await
+            .map_err(|_| SourceInfoError::RequestFailure {
+                file: file.to_owned(),
+            })?;
+        // Convert the response JsValue into a Response object.
+        let response: Response =
+            response
+                .dyn_into()
+                .map_err(|_| SourceInfoError::RequestFailure {
+                    file: file.to_owned(),
+                })?;
+        // Fetch only fails on network errors, so a 404 or similar has to be checked for.
+        if !response.ok() {
+            return Err(SourceInfoError::BadStatus {
+                file: file.to_owned(),
+                status: response.status(),
+                status_text: response.status_text(),
+            });
+        }
+
+        let bad_body = || SourceInfoError::BadRequestBody {
+            file: file.to_owned(),
+        };
+        let code: JsValue = JsFuture::from(response.text().map_err(|_| bad_body())?)
+            .await
+            .map_err(|_| bad_body())?;
+        code.as_string().ok_or_else(bad_body)
+    }
+}
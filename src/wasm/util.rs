@@ -61,3 +61,66 @@ where
                 })
         })
 }
+
+pub fn clear_element(element: web_sys::Element) {
+    element.set_inner_html("");
+}
+
+/// Turn the error into a javascript `Error` for the host page.
+/// Failures to fetch a source also have `source` and `status` properties, so that the page can
+/// say which file failed without picking apart the message.
+pub fn engine_error_to_js(error: &crate::engine::error::EngineError) -> JsValue {
+    use crate::engine::error::EngineError;
+
+    let js_error = js_sys::Error::new(&error.to_string());
+    let set_source = |object: &JsValue, error: &EngineError| {
+        if let EngineError::SourceFetch { source, status, .. } = error {
+            let status = status.map_or(JsValue::NULL, |status| JsValue::from(status));
+            // Setting properties on a fresh object can't fail
+            let _ = js_sys::Reflect::set(
+                object,
+                &JsValue::from_str("source"),
+                &JsValue::from_str(source),
+            );
+            let _ = js_sys::Reflect::set(object, &JsValue::from_str("status"), &status);
+        }
+    };
+    match error {
+        EngineError::SourceFetch { .. } => set_source(&js_error, error),
+        // Each failure as its own error in `failures`, with the first also on the error itself
+        EngineError::SourceFetches(errors) => {
+            let failures = js_sys::Array::new();
+            for error in errors {
+                let failure = js_sys::Error::new(&error.to_string());
+                set_source(&failure, error);
+                failures.push(&failure);
+            }
+            if let Some(first) = errors.first() {
+                set_source(&js_error, first);
+            }
+            let _ = js_sys::Reflect::set(&js_error, &JsValue::from_str("failures"), &failures);
+        }
+        _ => {}
+    }
+    js_error.into()
+}
+
+/// Turn the diagnostics into an array of plain objects for the host page.
+pub fn diagnostics_to_js(diagnostics: &[crate::diagnostics::Diagnostic]) -> JsValue {
+    let array = js_sys::Array::new();
+    for diagnostic in diagnostics {
+        let object = js_sys::Object::new();
+        let set = |key: &str, value: JsValue| {
+            // Setting properties on a fresh object can't fail
+            let _ = js_sys::Reflect::set(&object, &JsValue::from_str(key), &value);
+        };
+        set("file", JsValue::from_str(&diagnostic.file));
+        set("start", JsValue::from(diagnostic.start as u32));
+        set("end", JsValue::from(diagnostic.end as u32));
+        set("line", JsValue::from(diagnostic.line as u32));
+        set("column", JsValue::from(diagnostic.column as u32));
+        set("message", JsValue::from_str(&diagnostic.message));
+        array.push(&object);
+    }
+    array.into()
+}
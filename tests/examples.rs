@@ -0,0 +1,155 @@
+//! Plays through the example games, so that changes to the engine which break them are noticed.
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{path::PathBuf, rc::Rc};
+
+use aleph_naught::{
+    engine::storage::MemoryStorage,
+    frontend::headless::{Headless, HeadlessError},
+    playthrough::Playthrough,
+};
+
+#[test]
+fn demon() {
+    let game = Headless::from_files(&["examples/demon.rune"]).unwrap();
+    assert_eq!(game.scene().as_deref(), Some("town"));
+    assert_eq!(game.title(), "Town of Plenty");
+    assert_eq!(
+        game.choices(),
+        Some(vec!["Leave Town".to_owned(), "Enter Tavern".to_owned()])
+    );
+
+    game.choose_text("Enter Tavern").unwrap();
+    assert_eq!(game.title(), "Cheap Tavern");
+    assert!(game.text().contains("hooded man"));
+
+    game.choose_text("Talk to the Demon").unwrap();
+    assert_eq!(game.scene().as_deref(), Some("demon_conv"));
+    assert!(game.text().contains("lost pair of shoes"));
+
+    game.choose_text("Continue").unwrap();
+    game.choose_text("Leave Tavern").unwrap();
+    game.choose_text("Leave Town").unwrap();
+    assert_eq!(game.title(), "Forest of Darkness");
+    // Only offered once the demon has given the quest
+    game.choose_text("Search for Shoes").unwrap();
+    assert!(game.text().contains("carnivorous shoe"));
+    assert!(!game.is_waiting());
+    assert_eq!(game.error(), None);
+}
+
+#[test]
+fn demon_forest_without_quest() {
+    let game = Headless::from_files(&["examples/demon.rune"]).unwrap();
+    game.choose_text("Leave Town").unwrap();
+    assert_eq!(game.title(), "Forest of Darkness");
+    assert_eq!(
+        game.choose_text("Search for Shoes"),
+        Err(HeadlessError::UnknownChoice("Search for Shoes".to_owned()))
+    );
+    game.choose_text("Flee to Town").unwrap();
+    assert_eq!(game.title(), "Town of Plenty");
+}
+
+/// Create a character, then save in the town.
+fn aurum_new_game(game: &Headless) {
+    assert_eq!(game.title(), "Aleph: Game Engine");
+    game.choose_text("New Game").unwrap();
+    assert_eq!(game.title(), "Character Creation: Race");
+
+    game.choose_text("Human").unwrap();
+    assert_eq!(game.title(), "Character Creation: Race(Human)");
+    assert!(game.text().contains("Society:"));
+    game.choose_text("Accept").unwrap();
+    assert_eq!(game.title(), "Character Creation: Appearance");
+    game.choose_text("Tan").unwrap();
+    game.choose_text("Black").unwrap();
+
+    assert_eq!(game.title(), "Character Creation: Name");
+    assert!(matches!(
+        game.submit(""),
+        Err(HeadlessError::InvalidInput { .. })
+    ));
+    // Markup in the name is shown as it was typed
+    game.submit("[b]Sam").unwrap();
+    assert_eq!(game.title(), "Character Creation: Finished");
+    assert!(game.text().contains("Name: [b]Sam"));
+    assert!(game.text().contains("Race: Human"));
+
+    game.choose_text("Accept").unwrap();
+    assert_eq!(game.scene().as_deref(), Some("aurum_town"));
+    assert_eq!(game.title(), "Aurum: Town Center");
+
+    game.choose_text("Save Game").unwrap();
+    assert_eq!(game.title(), "Save Game");
+    assert!(matches!(game.check("../escape").unwrap(), Some(Err(_))));
+    assert!(matches!(
+        game.submit("../escape"),
+        Err(HeadlessError::InvalidInput { .. })
+    ));
+    assert_eq!(game.check("first save").unwrap(), Some(Ok(())));
+    game.submit("first save").unwrap();
+    assert_eq!(game.title(), "Aurum: Town Center");
+}
+
+#[test]
+fn aurum() {
+    let storage = Rc::new(MemoryStorage::default());
+    let sources = aleph_naught::load_files(&["examples/aurum.rune"]).unwrap();
+    let game = Headless::new(sources, storage.clone()).unwrap();
+    aurum_new_game(&game);
+    game.choose_text("Tavern").unwrap();
+    assert_eq!(game.title(), "Aurum: The Wet Iron Tavern");
+
+    // A new session with the same storage can load the save, back in the town
+    let sources = aleph_naught::load_files(&["examples/aurum.rune"]).unwrap();
+    let game = Headless::new(sources, storage).unwrap();
+    game.choose_text("Load Game").unwrap();
+    assert_eq!(game.title(), "Load Game");
+    game.choose_text("first save").unwrap();
+    assert_eq!(game.scene().as_deref(), Some("aurum_town"));
+    assert_eq!(game.title(), "Aurum: Town Center");
+    game.choose_text("Tavern").unwrap();
+    assert_eq!(game.title(), "Aurum: The Wet Iron Tavern");
+}
+
+#[test]
+fn aurum_without_saves() {
+    let game = Headless::from_files(&["examples/aurum.rune"]).unwrap();
+    game.choose_text("Load Game").unwrap();
+    assert_eq!(game.text(), "There are no saves to load.");
+    game.choose_text("Back").unwrap();
+    assert_eq!(game.title(), "Aleph: Game Engine");
+}
+
+/// Every playthrough file under `examples`, so that none of them go stale unnoticed.
+fn playthrough_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut directories = vec![PathBuf::from("examples")];
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                directories.push(path);
+            } else if path.extension().map_or(false, |ext| ext == "test") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn example_playthroughs() {
+    let files = playthrough_files();
+    assert!(!files.is_empty());
+    let mut failed = Vec::new();
+    for file in files {
+        let report = Playthrough::load(&file).unwrap().run();
+        if !report.passed() {
+            failed.push(format!("{}\n{}", file.display(), report));
+        }
+    }
+    assert!(failed.is_empty(), "\n{}", failed.join("\n"));
+}